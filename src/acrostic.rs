@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::crossword::{Cell, Crossword, Pos};
+use crate::matcher::Matcher;
+
+#[derive(Debug, Clone, Copy)]
+pub struct AcrosticOptions {
+    pub width: usize,
+    pub min_word_length: usize,
+    pub max_word_length: usize,
+    pub max_candidates: usize,
+    pub max_nodes: usize,
+    pub attempts: usize,
+}
+
+impl Default for AcrosticOptions {
+    fn default() -> Self {
+        Self {
+            width: 15,
+            min_word_length: 3,
+            max_word_length: 12,
+            max_candidates: 30,
+            max_nodes: 20_000,
+            attempts: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AcrosticClue {
+    pub initial: char,
+    pub word: String,
+    pub numbers: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Acrostic {
+    pub grid: Crossword,
+    pub cells: Vec<Pos>,
+    pub clues: Vec<AcrosticClue>,
+}
+
+impl Acrostic {
+    pub fn cell(&self, number: usize) -> Pos {
+        self.cells[number - 1]
+    }
+
+    pub fn clue_at(&self, number: usize) -> Option<(usize, usize)> {
+        self.clues.iter().enumerate().find_map(|(clue, entry)| {
+            entry.numbers.iter().position(|n| *n == number).map(|letter| (clue, letter))
+        })
+    }
+}
+
+pub fn make_acrostic(quotation: &str, source: &str, matcher: &Matcher, options: &AcrosticOptions, rng: &mut impl Rng) -> Option<Acrostic> {
+    let quote = letters(quotation);
+    let initials = letters(source);
+    if initials.is_empty() || quote.len() < initials.len() * options.min_word_length {
+        return None;
+    }
+    let mut remaining = [0usize; 26];
+    for c in &quote {
+        remaining[index(*c)] += 1;
+    }
+    let mut dictionary = vec![Vec::new(); 26];
+    for length in options.min_word_length..=options.max_word_length {
+        for initial in 'A'..='Z' {
+            let mut pattern = vec![None; length];
+            pattern[0] = Some(initial);
+            dictionary[index(initial)].extend(matcher.find(&pattern).into_iter().map(|word| (counts(&word), word)));
+        }
+    }
+
+    let by_counts = dictionary.iter()
+        .map(|words| {
+            let mut index: HashMap<[usize; 26], Vec<usize>> = HashMap::new();
+            for (i, (word_counts, _)) in words.iter().enumerate() {
+                index.entry(*word_counts).or_default().push(i);
+            }
+            index
+        })
+        .collect::<Vec<_>>();
+
+    let mut frequency = [1.0f64; 26];
+    for (word_counts, _) in dictionary.iter().flatten() {
+        for (frequency, count) in frequency.iter_mut().zip(word_counts) {
+            *frequency += *count as f64;
+        }
+    }
+    let frequency_total = frequency.iter().sum::<f64>();
+    for frequency in frequency.iter_mut() {
+        *frequency /= frequency_total;
+    }
+
+    for _ in 0..options.attempts.max(1) {
+        let mut search = Search {
+            options,
+            initials: &initials,
+            dictionary: &dictionary,
+            by_counts: &by_counts,
+            frequency,
+            chosen: vec![None; initials.len()],
+            nodes: 0,
+        };
+        let domains = initials.iter().map(|initial| (0..dictionary[index(*initial)].len()).collect::<Vec<_>>()).collect::<Vec<_>>();
+        if search.run(&mut remaining.clone(), &domains, rng) {
+            let words = search.chosen.into_iter().map(|word| word.unwrap()).collect::<Vec<_>>();
+            return Some(build(quotation, &quote, &initials, words, options, rng));
+        }
+    }
+    None
+}
+
+fn build(quotation: &str, quote: &[char], initials: &[char], words: Vec<String>, options: &AcrosticOptions, rng: &mut impl Rng) -> Acrostic {
+    let mut layout = Vec::new();
+    for c in quotation.chars() {
+        if c.is_ascii_alphabetic() {
+            layout.push(Some(c.to_ascii_uppercase()));
+        } else if layout.last().is_some_and(|last| last.is_some()) {
+            layout.push(None);
+        }
+    }
+    if layout.last() == Some(&None) {
+        layout.pop();
+    }
+    let width = options.width.max(1);
+    let height = layout.len().div_ceil(width);
+    let mut grid = Crossword::new(width, height);
+    let mut cells = Vec::with_capacity(quote.len());
+    for (i, pos) in grid.positions().collect::<Vec<_>>().into_iter().enumerate() {
+        grid[pos] = match layout.get(i) {
+            Some(Some(c)) => {
+                cells.push(pos);
+                Cell::Letter(*c)
+            },
+            Some(None) => Cell::Black,
+            None => Cell::Void,
+        };
+    }
+
+    let mut free = vec![Vec::new(); 26];
+    for (i, c) in quote.iter().enumerate() {
+        free[index(*c)].push(i + 1);
+    }
+    for numbers in free.iter_mut() {
+        numbers.shuffle(rng);
+    }
+    let clues = initials.iter().zip(words)
+        .map(|(initial, word)| {
+            let numbers = word.chars().map(|c| free[index(c)].pop().unwrap()).collect();
+            AcrosticClue { initial: *initial, word, numbers }
+        })
+        .collect();
+    Acrostic { grid, cells, clues }
+}
+
+struct Search<'a> {
+    options: &'a AcrosticOptions,
+    initials: &'a [char],
+    dictionary: &'a [Vec<([usize; 26], String)>],
+    by_counts: &'a [HashMap<[usize; 26], Vec<usize>>],
+    frequency: [f64; 26],
+    chosen: Vec<Option<String>>,
+    nodes: usize,
+}
+
+impl Search<'_> {
+    fn run(&mut self, remaining: &mut [usize; 26], domains: &[Vec<usize>], rng: &mut impl Rng) -> bool {
+        self.nodes += 1;
+        if self.nodes > self.options.max_nodes {
+            return false;
+        }
+        let pending = (0..self.initials.len()).filter(|i| self.chosen[*i].is_none()).collect::<Vec<_>>();
+        let total = remaining.iter().sum::<usize>();
+        if pending.is_empty() {
+            return total == 0;
+        }
+        if total < pending.len() * self.options.min_word_length || total > pending.len() * self.options.max_word_length {
+            return false;
+        }
+        let mut needed = [0usize; 26];
+        for i in &pending {
+            needed[index(self.initials[*i])] += 1;
+        }
+        if needed.iter().zip(remaining.iter()).any(|(needed, remaining)| needed > remaining) {
+            return false;
+        }
+
+        let mut domains = domains.to_vec();
+        for clue in &pending {
+            domains[*clue] = self.candidates(*clue, &domains[*clue], remaining, &needed, pending.len() == 1);
+        }
+        if pending.len() == 2 {
+            return self.finish_pair(pending[0], pending[1], &domains[pending[0]], remaining);
+        }
+
+        let clue = *pending.iter().min_by_key(|clue| domains[**clue].len()).unwrap();
+        let candidates = domains[clue].clone();
+        let target = total as f64 / pending.len() as f64;
+        let surplus = (0..26).map(|c| remaining[c] as f64 / total as f64 / self.frequency[c]).collect::<Vec<_>>();
+        let dictionary = &self.dictionary[index(self.initials[clue])];
+        let mut weighted = candidates.into_iter()
+            .map(|candidate| {
+                let (word_counts, word) = &dictionary[candidate];
+                let balance = (0..26).map(|c| word_counts[c] as f64 * surplus[c]).sum::<f64>() / word.len() as f64;
+                let length_penalty = 1.0 + (word.len() as f64 - target).abs() / target;
+                (candidate, balance / length_penalty * rng.gen_range(0.8..1.2))
+            })
+            .collect::<Vec<_>>();
+        weighted.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let candidates = weighted.into_iter().take(self.options.max_candidates).map(|(candidate, _)| candidate).collect::<Vec<_>>();
+        for candidate in candidates {
+            let (word_counts, word) = &dictionary[candidate];
+            for (remaining, count) in remaining.iter_mut().zip(word_counts) {
+                *remaining -= count;
+            }
+            self.chosen[clue] = Some(word.clone());
+            if self.run(remaining, &domains, rng) {
+                return true;
+            }
+            self.chosen[clue] = None;
+            for (remaining, count) in remaining.iter_mut().zip(word_counts) {
+                *remaining += count;
+            }
+            if self.nodes > self.options.max_nodes {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn finish_pair(&mut self, first: usize, second: usize, candidates: &[usize], remaining: &[usize; 26]) -> bool {
+        let first_words = &self.dictionary[index(self.initials[first])];
+        let second_words = &self.dictionary[index(self.initials[second])];
+        for candidate in candidates {
+            let (word_counts, word) = &first_words[*candidate];
+            let mut rest = *remaining;
+            for (rest, count) in rest.iter_mut().zip(word_counts) {
+                *rest -= count;
+            }
+            let Some(matches) = self.by_counts[index(self.initials[second])].get(&rest) else {
+                continue;
+            };
+            let used = |other: &String| other == word || self.chosen.iter().flatten().any(|chosen| chosen == other);
+            if let Some(other) = matches.iter().map(|i| &second_words[*i].1).find(|other| !used(other)) {
+                self.chosen[first] = Some(word.clone());
+                self.chosen[second] = Some(other.clone());
+                return true;
+            }
+        }
+        false
+    }
+
+    fn candidates(&self, clue: usize, domain: &[usize], remaining: &[usize; 26], needed: &[usize; 26], last: bool) -> Vec<usize> {
+        let initial = index(self.initials[clue]);
+        domain.iter()
+            .map(|i| (*i, &self.dictionary[initial][*i]))
+            .filter(|(_, (_, word))| !self.chosen.iter().flatten().any(|chosen| chosen == word))
+            .filter(|(_, (word_counts, _))| {
+                (0..26).all(|c| {
+                    let reserved = if c == initial { needed[c] - 1 } else { needed[c] };
+                    word_counts[c] + reserved <= remaining[c] && (!last || word_counts[c] == remaining[c])
+                })
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+fn letters(text: &str) -> Vec<char> {
+    text.chars().filter(|c| c.is_ascii_alphabetic()).map(|c| c.to_ascii_uppercase()).collect()
+}
+
+fn counts(word: &str) -> [usize; 26] {
+    let mut result = [0; 26];
+    for c in word.chars() {
+        result[index(c)] += 1;
+    }
+    result
+}
+
+fn index(c: char) -> usize {
+    (c as u8 - b'A') as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+
+    fn matcher() -> Matcher {
+        Matcher::from_words(["CAST", "ATE", "TARS", "CAT", "TEA", "ART", "CRATE"])
+    }
+
+    #[test]
+    fn clues_use_every_letter_of_the_quotation_once() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = AcrosticOptions { width: 5, ..Default::default() };
+        let acrostic = make_acrostic("Cats eat rats.", "C. A. T.", &matcher(), &options, &mut rng).unwrap();
+        let initials = acrostic.clues.iter().map(|clue| clue.initial).collect::<String>();
+        assert_eq!(initials, "CAT");
+        let words = acrostic.clues.iter().map(|clue| clue.word.as_str()).collect::<Vec<_>>();
+        assert_eq!(words, ["CAST", "ATE", "TARS"]);
+
+        let mut numbers = acrostic.clues.iter().flat_map(|clue| clue.numbers.iter().copied()).collect::<Vec<_>>();
+        numbers.sort();
+        assert_eq!(numbers, (1..=11).collect::<Vec<_>>());
+        for (i, clue) in acrostic.clues.iter().enumerate() {
+            for (letter, (c, number)) in clue.word.chars().zip(&clue.numbers).enumerate() {
+                assert_eq!(acrostic.grid[acrostic.cell(*number)], Cell::Letter(c));
+                assert_eq!(acrostic.clue_at(*number), Some((i, letter)));
+            }
+        }
+    }
+
+    #[test]
+    fn grid_spells_the_quotation_with_blocks_between_words() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = AcrosticOptions { width: 5, ..Default::default() };
+        let acrostic = make_acrostic("Cats eat rats.", "CAT", &matcher(), &options, &mut rng).unwrap();
+        assert_eq!((acrostic.grid.width(), acrostic.grid.height()), (5, 3));
+        assert_eq!(acrostic.cells.len(), 11);
+        assert_eq!(acrostic.grid[Pos(4, 0)], Cell::Black);
+        assert_eq!(acrostic.grid[Pos(3, 1)], Cell::Black);
+        assert_eq!(acrostic.grid[Pos(3, 2)], Cell::Void);
+        assert_eq!(acrostic.cell(5), Pos(0, 1));
+    }
+
+    #[test]
+    fn impossible_sources_give_none() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = AcrosticOptions::default();
+        assert!(make_acrostic("Cats eat rats.", "CATS", &matcher(), &options, &mut rng).is_none());
+        assert!(make_acrostic("Cats eat rats.", "CAR", &matcher(), &options, &mut rng).is_none());
+        assert!(make_acrostic("Ca", "C", &matcher(), &options, &mut rng).is_none());
+    }
+}
@@ -1,8 +1,9 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::ops::{Index, IndexMut};
 use rand::Rng;
 
+use crate::diagnose::{diagnose, Diagnosis};
 use crate::matcher::Matcher;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -10,6 +11,57 @@ pub enum Cell {
     Empty,
     Black,
     Letter(char),
+    Rebus(Rebus),
+    Void,
+    Clue,
+}
+
+impl Cell {
+    pub fn is_open(&self) -> bool {
+        matches!(self, Cell::Empty | Cell::Letter(_) | Cell::Rebus(_))
+    }
+}
+
+pub const MAX_REBUS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rebus {
+    letters: [char; MAX_REBUS],
+    len: usize,
+}
+
+impl Rebus {
+    pub fn new(letters: &str) -> Self {
+        let mut result = Self { letters: ['\0'; MAX_REBUS], len: 0 };
+        for c in letters.chars() {
+            if !c.is_ascii_alphabetic() {
+                panic!("Invalid rebus character: {}", c);
+            }
+            if result.len == MAX_REBUS {
+                panic!("Rebus too long: {}", letters);
+            }
+            result.letters[result.len] = c.to_ascii_uppercase();
+            result.len += 1;
+        }
+        if result.len < 2 {
+            panic!("Rebus must have at least two letters: {}", letters);
+        }
+        result
+    }
+
+    pub fn letters(&self) -> &[char] {
+        &self.letters[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl fmt::Display for Rebus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.letters().iter().try_for_each(|c| write!(f, "{}", c))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -17,6 +69,26 @@ pub struct Crossword {
     width: usize,
     height: usize,
     grid: Vec<Vec<Cell>>,
+    run_policies: Vec<RunPolicy>,
+    locked: Vec<Vec<bool>>,
+    bars: HashSet<(Pos, Direction)>,
+    attributes: HashMap<Pos, CellAttributes>,
+    clues: HashMap<(Pos, Direction), String>,
+    arrows: HashMap<Pos, Vec<ArrowClue>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CellAttributes {
+    pub circle: bool,
+    pub shade: bool,
+    pub color: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl CellAttributes {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -28,13 +100,136 @@ pub enum Direction {
     Down,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    None,
+    Rotational,
+    Mirror,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Arrow {
+    Right,
+    Down,
+    DownThenRight,
+    RightThenDown,
+}
+
+impl Arrow {
+    pub fn start(&self, pos: Pos) -> Pos {
+        match self {
+            Arrow::Right | Arrow::RightThenDown => Pos(pos.0 + 1, pos.1),
+            Arrow::Down | Arrow::DownThenRight => Pos(pos.0, pos.1 + 1),
+        }
+    }
+
+    pub fn direction(&self) -> Direction {
+        match self {
+            Arrow::Right | Arrow::DownThenRight => Direction::Across,
+            Arrow::Down | Arrow::RightThenDown => Direction::Down,
+        }
+    }
+
+    pub fn symbol(&self) -> char {
+        match self {
+            Arrow::Right => '→',
+            Arrow::Down => '↓',
+            Arrow::DownThenRight => '↳',
+            Arrow::RightThenDown => '↴',
+        }
+    }
+
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        [Arrow::Right, Arrow::Down, Arrow::DownThenRight, Arrow::RightThenDown].into_iter().find(|arrow| arrow.symbol() == symbol)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArrowClue {
+    pub arrow: Arrow,
+    pub clue: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RunPolicy {
+    Unchecked,
+    Dictionary,
+    Free,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Slot {
+    pub pos: Pos,
+    pub direction: Direction,
+    pub length: usize,
+}
+
+impl Slot {
+    pub fn new(pos: Pos, direction: Direction, length: usize) -> Self {
+        Self { pos, direction, length }
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = Pos> {
+        let (pos, direction) = (self.pos, self.direction);
+        (0..self.length).map(move |i| match direction {
+            Direction::Across => Pos(pos.0 + i, pos.1),
+            Direction::Down => Pos(pos.0, pos.1 + i),
+        })
+    }
+}
+
+impl Symmetry {
+    pub fn partner(&self, pos: Pos, width: usize, height: usize) -> Pos {
+        match self {
+            Symmetry::None => pos,
+            Symmetry::Rotational => Pos(width - 1 - pos.0, height - 1 - pos.1),
+            Symmetry::Mirror => Pos(width - 1 - pos.0, pos.1),
+        }
+    }
+
+    pub fn partner_slot(&self, slot: Slot, width: usize, height: usize) -> Slot {
+        let first = self.partner(slot.pos, width, height);
+        let last = self.partner(slot.cells().last().unwrap_or(slot.pos), width, height);
+        Slot::new(Pos(first.0.min(last.0), first.1.min(last.1)), slot.direction, slot.length)
+    }
+}
+
 impl Crossword {
     pub fn new(width: usize, height: usize) -> Self {
         Self {
             width,
             height,
             grid: vec![vec![Cell::Empty; width]; height],
+            run_policies: Vec::new(),
+            locked: vec![vec![false; width]; height],
+            bars: HashSet::new(),
+            attributes: HashMap::new(),
+            clues: HashMap::new(),
+            arrows: HashMap::new(),
+        }
+    }
+
+    pub fn with_mask(width: usize, height: usize, inside: impl Fn(Pos) -> bool) -> Self {
+        let mut crossword = Self::new(width, height);
+        for pos in crossword.positions().collect::<Vec<_>>() {
+            if !inside(pos) {
+                crossword[pos] = Cell::Void;
+            }
         }
+        crossword
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Pos> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| Pos(x, y)))
     }
 
     pub fn from_str(s: &str) -> Self {
@@ -42,8 +237,66 @@ impl Crossword {
         let mut grid: Vec<Vec<Cell>> = vec![vec![]];
         let mut width: Option<usize> = None;
         let mut x = 0;
+        let mut rebus: Option<String> = None;
+        let mut bars = HashSet::new();
+        let mut attributes: HashMap<Pos, CellAttributes> = HashMap::new();
+        let mut braced: Option<String> = None;
+        let mut escaped = false;
+        let mut arrows: HashMap<Pos, Vec<ArrowClue>> = HashMap::new();
         for c in s.chars() {
+            if let Some(content) = braced.as_mut() {
+                if escaped {
+                    content.push(c);
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == '}' {
+                    let pos = Pos(x - 1, grid.len() - 1);
+                    match content.split_once(':') {
+                        Some(("c", color)) => attributes.entry(pos).or_default().color = Some(color.to_string()),
+                        Some(("t", tag)) => attributes.entry(pos).or_default().tag = Some(tag.to_string()),
+                        Some(("q", clue)) => match arrows.get_mut(&pos).and_then(|arrows| arrows.last_mut()) {
+                            Some(entry) => entry.clue = clue.to_string(),
+                            None => panic!("Invalid pattern: clue without arrow"),
+                        },
+                        _ => panic!("Invalid attribute: {}", content),
+                    }
+                    braced = None;
+                } else {
+                    content.push(c);
+                }
+                continue;
+            }
+            if let Some(letters) = rebus.as_mut() {
+                if c == ']' {
+                    grid.last_mut().unwrap().push(Cell::Rebus(Rebus::new(letters)));
+                    rebus = None;
+                    x += 1;
+                } else {
+                    letters.push(c);
+                }
+                continue;
+            }
             match c {
+                '[' => rebus = Some(String::new()),
+                '@' | '*' | '{' => {
+                    if x == 0 {
+                        panic!("Invalid pattern: attribute before first cell");
+                    }
+                    let pos = Pos(x - 1, grid.len() - 1);
+                    match c {
+                        '@' => attributes.entry(pos).or_default().circle = true,
+                        '*' => attributes.entry(pos).or_default().shade = true,
+                        _ => braced = Some(String::new()),
+                    }
+                },
+                '|' | '_' => {
+                    if x == 0 {
+                        panic!("Invalid pattern: bar before first cell");
+                    }
+                    let direction = if c == '|' { Direction::Across } else { Direction::Down };
+                    bars.insert((Pos(x - 1, grid.len() - 1), direction));
+                },
                 '□' => {
                     grid.last_mut().unwrap().push(Cell::Empty);
                     x += 1;
@@ -52,6 +305,21 @@ impl Crossword {
                     grid.last_mut().unwrap().push(Cell::Black);
                     x += 1;
                 },
+                '·' => {
+                    grid.last_mut().unwrap().push(Cell::Void);
+                    x += 1;
+                },
+                '#' => {
+                    grid.last_mut().unwrap().push(Cell::Clue);
+                    x += 1;
+                },
+                '→' | '↓' | '↳' | '↴' => {
+                    if x == 0 || grid.last().unwrap()[x - 1] != Cell::Clue {
+                        panic!("Invalid pattern: arrow outside clue cell");
+                    }
+                    let arrow = Arrow::from_symbol(c).unwrap();
+                    arrows.entry(Pos(x - 1, grid.len() - 1)).or_default().push(ArrowClue { arrow, clue: String::new() });
+                },
                 '\n' => {
                     if let Some(w) = width {
                         if x != w {
@@ -64,10 +332,10 @@ impl Crossword {
                     grid.push(Vec::new());
                 },
                 _ => {
-                    if c >= 'A' && c <= 'Z' {
+                    if c.is_ascii_uppercase() {
                         grid.last_mut().unwrap().push(Cell::Letter(c));
                         x += 1;
-                    } else if c >= 'a' && c <= 'z' {
+                    } else if c.is_ascii_lowercase() {
                         grid.last_mut().unwrap().push(Cell::Letter(c.to_ascii_uppercase()));
                         x += 1;
                     } else if !c.is_whitespace() {
@@ -76,46 +344,41 @@ impl Crossword {
                 },
             }
         }
+        if rebus.is_some() || braced.is_some() {
+            panic!("Invalid pattern: unterminated rebus or attribute");
+        }
+        let width = width.expect("Invalid pattern: empty pattern");
         Self {
-            width: width.expect("Invalid pattern: empty pattern"),
+            width,
             height: grid.len(),
+            locked: vec![vec![false; width]; grid.len()],
             grid,
+            run_policies: Vec::new(),
+            bars,
+            attributes,
+            clues: HashMap::new(),
+            arrows,
         }
     }
 
     pub fn set_word(&mut self, word: &str, pos: Pos, direction: Direction) {
-        let (x, y) = (pos.0, pos.1);
-        let mut x = x;
-        let mut y = y;
-        for c in word.chars() {
-            self[Pos(x, y)] = Cell::Letter(c);
-            match direction {
-                Direction::Across => x += 1,
-                Direction::Down => y += 1,
-            }
-        }
+        let pattern = word.chars().map(Some).collect::<Vec<_>>();
+        self.set_pattern(direction, pos, &pattern);
     }
 
     pub fn across_positions(&self) -> HashMap<Pos, usize> {
         let mut positions = HashMap::new();
         for y in 0..self.height {
-            let mut start: Pos = Pos(0, y);
+            let mut start: Option<Pos> = None;
             for x in 0..self.width {
-                match self[Pos(x, y)] {
-                    Cell::Empty | Cell::Letter(_) => {
-                        if x == 0 || self[Pos(x - 1, y)] == Cell::Black {
-                            start = Pos(x, y);
-                        }
-                    },
-                    Cell::Black => {
-                        if x != 0 && self[Pos(x - 1, y)] != Cell::Black {
-                            positions.insert(start, x - start.0);
-                        }
-                    },
+                if !self[Pos(x, y)].is_open() {
+                    continue;
+                }
+                let first = *start.get_or_insert(Pos(x, y));
+                if x + 1 == self.width || !self[Pos(x + 1, y)].is_open() || self.has_bar(Pos(x, y), Direction::Across) {
+                    positions.insert(first, x + 1 - first.0);
+                    start = None;
                 }
-            }
-            if self[Pos(self.width-1, y)] != Cell::Black && start.0 < self.width {
-                positions.insert(start, self.width - start.0);
             }
         }
         positions
@@ -124,39 +387,201 @@ impl Crossword {
     pub fn down_positions(&self) -> HashMap<Pos, usize> {
         let mut positions = HashMap::new();
         for x in 0..self.width {
-            let mut start: Pos = Pos(x, 0);
+            let mut start: Option<Pos> = None;
             for y in 0..self.height {
-                match self[Pos(x, y)] {
-                    Cell::Empty | Cell::Letter(_) => {
-                        if y == 0 || self[Pos(x, y - 1)] == Cell::Black {
-                            start = Pos(x, y);
-                        }
-                    },
-                    Cell::Black => {
-                        if y != 0 && self[Pos(x, y - 1)] != Cell::Black {
-                            positions.insert(start, y - start.1);
-                        }
-                    },
+                if !self[Pos(x, y)].is_open() {
+                    continue;
+                }
+                let first = *start.get_or_insert(Pos(x, y));
+                if y + 1 == self.height || !self[Pos(x, y + 1)].is_open() || self.has_bar(Pos(x, y), Direction::Down) {
+                    positions.insert(first, y + 1 - first.1);
+                    start = None;
                 }
-            }
-            if self[Pos(x, self.height-1)] != Cell::Black && start.1 < self.height {
-                positions.insert(start, self.height - start.1);
             }
         }
         positions
     }
 
+    pub fn has_bar(&self, pos: Pos, direction: Direction) -> bool {
+        self.bars.contains(&(pos, direction))
+    }
+
+    pub fn set_bar(&mut self, pos: Pos, direction: Direction, bar: bool) {
+        if bar {
+            self.bars.insert((pos, direction));
+        } else {
+            self.bars.remove(&(pos, direction));
+        }
+    }
+
+    pub fn is_barred(&self) -> bool {
+        !self.bars.is_empty()
+    }
+
+    pub fn attributes(&self, pos: Pos) -> Option<&CellAttributes> {
+        self.attributes.get(&pos)
+    }
+
+    pub fn attributes_mut(&mut self, pos: Pos) -> &mut CellAttributes {
+        self.attributes.entry(pos).or_default()
+    }
+
+    pub fn set_attributes(&mut self, pos: Pos, attributes: CellAttributes) {
+        if attributes.is_empty() {
+            self.attributes.remove(&pos);
+        } else {
+            self.attributes.insert(pos, attributes);
+        }
+    }
+
+    pub fn clue(&self, slot: Slot) -> Option<&str> {
+        self.clues.get(&(slot.pos, slot.direction)).map(|clue| clue.as_str()).or_else(|| {
+            self.arrow_slots().into_iter()
+                .find(|(_, _, arrow_slot)| *arrow_slot == slot)
+                .and_then(|(pos, arrow, _)| self.arrows(pos).iter().find(|entry| entry.arrow == arrow))
+                .map(|entry| entry.clue.as_str())
+        })
+    }
+
+    pub fn set_clue(&mut self, slot: Slot, clue: &str) {
+        self.clues.insert((slot.pos, slot.direction), clue.to_string());
+    }
+
+    pub fn is_arrowword(&self) -> bool {
+        !self.arrows.is_empty()
+    }
+
+    pub fn arrows(&self, pos: Pos) -> &[ArrowClue] {
+        self.arrows.get(&pos).map_or(&[], |arrows| arrows.as_slice())
+    }
+
+    pub fn add_arrow(&mut self, pos: Pos, arrow: Arrow, clue: &str) {
+        self[pos] = Cell::Clue;
+        self.arrows.entry(pos).or_default().push(ArrowClue { arrow, clue: clue.to_string() });
+    }
+
+    pub fn set_arrow_clue(&mut self, pos: Pos, arrow: Arrow, clue: &str) {
+        if let Some(entry) = self.arrows.get_mut(&pos).and_then(|arrows| arrows.iter_mut().find(|entry| entry.arrow == arrow)) {
+            entry.clue = clue.to_string();
+        }
+    }
+
+    pub fn arrow_slots(&self) -> Vec<(Pos, Arrow, Slot)> {
+        let mut result = Vec::new();
+        for pos in self.positions() {
+            for entry in self.arrows(pos) {
+                let start = entry.arrow.start(pos);
+                if start.0 >= self.width || start.1 >= self.height || !self[start].is_open() {
+                    continue;
+                }
+                let direction = entry.arrow.direction();
+                let mut length = 1;
+                let mut last = start;
+                loop {
+                    let next = match direction {
+                        Direction::Across => Pos(last.0 + 1, last.1),
+                        Direction::Down => Pos(last.0, last.1 + 1),
+                    };
+                    if next.0 >= self.width || next.1 >= self.height || !self[next].is_open() || self.has_bar(last, direction) {
+                        break;
+                    }
+                    length += 1;
+                    last = next;
+                }
+                result.push((pos, entry.arrow, Slot::new(start, direction, length)));
+            }
+        }
+        result
+    }
+
+    pub fn circled_cells(&self) -> Vec<Pos> {
+        self.positions().filter(|pos| self.attributes(*pos).is_some_and(|attributes| attributes.circle)).collect()
+    }
+
+    pub fn circled_letters(&self) -> Vec<Option<char>> {
+        let cells = self.circled_cells().into_iter().filter(|pos| self[*pos].is_open()).collect::<Vec<_>>();
+        self.get_cells_pattern(&cells)
+    }
+
+    pub fn run_policy(&self, length: usize) -> RunPolicy {
+        self.run_policies.get(length).copied().unwrap_or(RunPolicy::Dictionary)
+    }
+
+    pub fn set_run_policy(&mut self, length: usize, policy: RunPolicy) {
+        if length >= self.run_policies.len() {
+            self.run_policies.resize(length + 1, RunPolicy::Dictionary);
+        }
+        self.run_policies[length] = policy;
+    }
+
+    pub fn slots(&self) -> Vec<Slot> {
+        if self.is_arrowword() {
+            let mut slots = self.arrow_slots().into_iter()
+                .map(|(_, _, slot)| slot)
+                .filter(|slot| self.run_policy(slot.length) != RunPolicy::Unchecked)
+                .collect::<Vec<_>>();
+            slots.sort_by_key(|slot| (slot.pos.1, slot.pos.0, slot.direction == Direction::Down));
+            slots.dedup();
+            return slots;
+        }
+        let across = self.across_positions().into_iter().map(|(pos, length)| Slot::new(pos, Direction::Across, length));
+        let down = self.down_positions().into_iter().map(|(pos, length)| Slot::new(pos, Direction::Down, length));
+        let mut slots = across.chain(down)
+            .filter(|slot| self.run_policy(slot.length) != RunPolicy::Unchecked)
+            .collect::<Vec<_>>();
+        slots.sort_by_key(|slot| (slot.pos.1, slot.pos.0, slot.direction == Direction::Down));
+        slots
+    }
+
+    pub fn dictionary_slots(&self) -> Vec<Slot> {
+        self.slots().into_iter().filter(|slot| self.run_policy(slot.length) == RunPolicy::Dictionary).collect()
+    }
+
+    pub fn run_at(&self, pos: Pos, direction: Direction) -> Option<Slot> {
+        if !self[pos].is_open() {
+            return None;
+        }
+        let step_back = |pos: Pos| match direction {
+            Direction::Across => (pos.0 > 0).then(|| Pos(pos.0 - 1, pos.1)),
+            Direction::Down => (pos.1 > 0).then(|| Pos(pos.0, pos.1 - 1)),
+        };
+        let mut start = pos;
+        while let Some(prev) = step_back(start).filter(|prev| self[*prev].is_open() && !self.has_bar(*prev, direction)) {
+            start = prev;
+        }
+        let mut length = 1;
+        loop {
+            let (last, next) = match direction {
+                Direction::Across => (Pos(start.0 + length - 1, start.1), Pos(start.0 + length, start.1)),
+                Direction::Down => (Pos(start.0, start.1 + length - 1), Pos(start.0, start.1 + length)),
+            };
+            if next.0 >= self.width || next.1 >= self.height || !self[next].is_open() || self.has_bar(last, direction) {
+                break;
+            }
+            length += 1;
+        }
+        Some(Slot::new(start, direction, length))
+    }
+
+    pub fn numbered_slots(&self) -> Vec<(usize, Slot)> {
+        let mut result = Vec::new();
+        let mut number = 0;
+        let mut last_pos = None;
+        for slot in self.slots() {
+            if last_pos != Some(slot.pos) {
+                number += 1;
+                last_pos = Some(slot.pos);
+            }
+            result.push((number, slot));
+        }
+        result
+    }
+
     pub fn get_across(&self, pos: Pos, length: usize) -> Vec<Option<char>> {
         let mut result = Vec::with_capacity(length);
-        let mut x = pos.0;
         let y = pos.1;
-        for _ in 0..length {
-            result.push(match self[Pos(x, y)] {
-                Cell::Empty => None,
-                Cell::Letter(c) => Some(c),
-                Cell::Black => panic!("Black cell in pattern"),
-            });
-            x += 1;
+        for x in pos.0..pos.0 + length {
+            self.extend_pattern(Pos(x, y), &mut result);
         }
         result
     }
@@ -164,29 +589,17 @@ impl Crossword {
     pub fn get_down(&self, pos: Pos, length: usize) -> Vec<Option<char>> {
         let mut result = Vec::with_capacity(length);
         let x = pos.0;
-        let mut y = pos.1;
-        for _ in 0..length {
-            result.push(match self[Pos(x, y)] {
-                Cell::Empty => None,
-                Cell::Letter(c) => Some(c),
-                Cell::Black => panic!("Black cell in pattern"),
-            });
-            y += 1;
+        for y in pos.1..pos.1 + length {
+            self.extend_pattern(Pos(x, y), &mut result);
         }
         result
     }
     
     pub fn get_pattern_across(&self, pos: Pos, length: usize) -> Vec<Option<char>> {
         let mut result = Vec::with_capacity(length);
-        let mut x = pos.0;
         let y = pos.1;
-        for _ in 0..length {
-            result.push(match self[Pos(x, y)] {
-                Cell::Empty => None,
-                Cell::Letter(c) => Some(c),
-                Cell::Black => panic!("Black cell in pattern"),
-            });
-            x += 1;
+        for x in pos.0..pos.0 + length {
+            self.extend_pattern(Pos(x, y), &mut result);
         }
         result
     }
@@ -194,18 +607,30 @@ impl Crossword {
     pub fn get_pattern_down(&self, pos: Pos, length: usize) -> Vec<Option<char>> {
         let mut result = Vec::with_capacity(length);
         let x = pos.0;
-        let mut y = pos.1;
-        for _ in 0..length {
-            result.push(match self[Pos(x, y)] {
-                Cell::Empty => None,
-                Cell::Letter(c) => Some(c),
-                Cell::Black => panic!("Black cell in pattern"),
-            });
-            y += 1;
+        for y in pos.1..pos.1 + length {
+            self.extend_pattern(Pos(x, y), &mut result);
         }
         result
     }
 
+    fn extend_pattern(&self, pos: Pos, pattern: &mut Vec<Option<char>>) {
+        match self[pos] {
+            Cell::Empty => pattern.push(None),
+            Cell::Letter(c) => pattern.push(Some(c)),
+            Cell::Rebus(rebus) => pattern.extend(rebus.letters().iter().map(|c| Some(*c))),
+            Cell::Black | Cell::Void | Cell::Clue => panic!("Black cell in pattern"),
+        }
+    }
+
+    pub fn pattern_cells(&self, slot: Slot) -> Vec<Pos> {
+        slot.cells()
+            .flat_map(|pos| match self[pos] {
+                Cell::Rebus(rebus) => vec![pos; rebus.len()],
+                _ => vec![pos],
+            })
+            .collect()
+    }
+
     pub fn get_pattern(&self, direction: Direction, pos: Pos, length: usize) -> Vec<Option<char>> {
         match direction {
             Direction::Across => self.get_pattern_across(pos, length),
@@ -214,27 +639,43 @@ impl Crossword {
     }
 
     pub fn set_pattern_across(&mut self, pos: Pos, pattern: &[Option<char>]) {
-        let mut x = pos.0;
         let y = pos.1;
-        for c in pattern {
-            self[Pos(x, y)] = match c {
-                Some(c) => Cell::Letter(*c),
-                None => Cell::Empty,
-            };
-            x += 1;
-        }
+        self.set_pattern_cells((pos.0..).map(|x| Pos(x, y)), pattern);
     }
 
     pub fn set_pattern_down(&mut self, pos: Pos, pattern: &[Option<char>]) {
         let x = pos.0;
-        let mut y = pos.1;
-        for c in pattern {
-            self[Pos(x, y)] = match c {
-                Some(c) => Cell::Letter(*c),
-                None => Cell::Empty,
-            };
-            y += 1;
+        self.set_pattern_cells((pos.1..).map(|y| Pos(x, y)), pattern);
+    }
+
+    fn set_pattern_cells(&mut self, cells: impl Iterator<Item = Pos>, mut pattern: &[Option<char>]) {
+        for pos in cells {
+            if pattern.is_empty() {
+                break;
+            }
+            match self[pos] {
+                Cell::Rebus(rebus) => pattern = &pattern[rebus.len().min(pattern.len())..],
+                _ => {
+                    self[pos] = match pattern[0] {
+                        Some(c) => Cell::Letter(c),
+                        None => Cell::Empty,
+                    };
+                    pattern = &pattern[1..];
+                },
+            }
+        }
+    }
+
+    pub fn get_cells_pattern(&self, cells: &[Pos]) -> Vec<Option<char>> {
+        let mut result = Vec::with_capacity(cells.len());
+        for pos in cells {
+            self.extend_pattern(*pos, &mut result);
         }
+        result
+    }
+
+    pub fn set_cells_pattern(&mut self, cells: &[Pos], pattern: &[Option<char>]) {
+        self.set_pattern_cells(cells.iter().copied(), pattern);
     }
 
     pub fn set_pattern(&mut self, direction: Direction, pos: Pos, pattern: &[Option<char>]) {
@@ -261,18 +702,50 @@ impl IndexMut<Pos> for Crossword {
 
 impl fmt::Display for Crossword {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for (i, row) in self.grid.iter().enumerate() {
+        for (y, row) in self.grid.iter().enumerate() {
             for (i, cell) in row.iter().enumerate() {
                 match cell {
                     Cell::Empty => write!(f, "□")?,
                     Cell::Black => write!(f, "■")?,
+                    Cell::Void => write!(f, "·")?,
+                    Cell::Clue => {
+                        write!(f, "#")?;
+                        for entry in self.arrows(Pos(i, y)) {
+                            write!(f, "{}", entry.arrow.symbol())?;
+                            if !entry.clue.is_empty() {
+                                write!(f, "{{q:{}}}", escape_braced(&entry.clue))?;
+                            }
+                        }
+                    },
                     Cell::Letter(c) => write!(f, "{}", c)?,
+                    Cell::Rebus(rebus) => write!(f, "[{}]", rebus)?,
+                }
+                if let Some(attributes) = self.attributes(Pos(i, y)) {
+                    if attributes.circle {
+                        write!(f, "@")?;
+                    }
+                    if attributes.shade {
+                        write!(f, "*")?;
+                    }
+                    if let Some(color) = &attributes.color {
+                        write!(f, "{{c:{}}}", escape_braced(color))?;
+                    }
+                    if let Some(tag) = &attributes.tag {
+                        write!(f, "{{t:{}}}", escape_braced(tag))?;
+                    }
+                }
+                if self.has_bar(Pos(i, y), Direction::Down) {
+                    write!(f, "_")?;
                 }
                 if i < self.width - 1 {
-                    write!(f, " ")?;
+                    if self.has_bar(Pos(i, y), Direction::Across) {
+                        write!(f, "|")?;
+                    } else {
+                        write!(f, " ")?;
+                    }
                 }
             }
-            if i < self.height - 1 {
+            if y < self.height - 1 {
                 writeln!(f)?;
             }
         }
@@ -280,82 +753,234 @@ impl fmt::Display for Crossword {
     }
 }
 
+fn parse_clue_line(line: &str) -> Option<((Pos, Direction), String)> {
+    let (key, clue) = line.split_once(": ")?;
+    let (coordinates, direction) = key.split_once(' ')?;
+    let (x, y) = coordinates.split_once(',')?;
+    let direction = match direction {
+        "across" => Direction::Across,
+        "down" => Direction::Down,
+        _ => return None,
+    };
+    let mut text = String::new();
+    let mut chars = clue.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => text.push('\n'),
+            escaped => text.push(escaped),
+        }
+    }
+    Some(((Pos(x.parse().ok()?, y.parse().ok()?), direction), text))
+}
+
+fn escape_braced(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('}', "\\}")
+}
+
 impl Crossword {
-    pub fn fill(&mut self, matcher: &Matcher, rng: &mut impl Rng) -> bool {
-        self.fill_recursive(matcher, &self.across_positions().into_iter().collect(), &self.down_positions().into_iter().collect(), rng, &mut HashMap::new())
+    pub fn black_count(&self) -> usize {
+        self.positions().filter(|pos| self[*pos] == Cell::Black).count()
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.positions().filter(|pos| self[*pos] != Cell::Void).count()
     }
 
-    fn fill_recursive(&mut self, matcher: &Matcher, across: &Vec<(Pos, usize)>, down: &Vec<(Pos, usize)>, rng: &mut impl Rng, cache: &mut HashMap<Vec<Option<char>>, usize>) -> bool {
-        let pos = self.choice_pos(matcher, across, down, cache);
-        if pos.is_none() {
-            return true;
+    pub fn regions(&self) -> Vec<Vec<Pos>> {
+        let mut visited = vec![vec![false; self.width]; self.height];
+        let mut regions = Vec::new();
+        for start in self.positions() {
+            if !self[start].is_open() || visited[start.1][start.0] {
+                continue;
+            }
+            let mut region = Vec::new();
+            let mut stack = vec![start];
+            visited[start.1][start.0] = true;
+            while let Some(pos) = stack.pop() {
+                region.push(pos);
+                for next in self.neighbours(pos) {
+                    if self[next].is_open() && !visited[next.1][next.0] {
+                        visited[next.1][next.0] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+            regions.push(region);
         }
-        let (pos, length, index, direction) = pos.unwrap();
-        let pattern = self.get_pattern(direction, pos, length);
-        let matches = matcher.find_vec_random(pattern.as_slice(), rng);
-        if matches.is_empty() {
-            return false;
+        regions
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.regions().len() <= 1
+    }
+
+    pub fn unchecked_cells(&self) -> Vec<Pos> {
+        let mut across = vec![vec![0; self.width]; self.height];
+        let mut down = vec![vec![0; self.width]; self.height];
+        for (pos, length) in self.across_positions() {
+            across[pos.1][pos.0..pos.0 + length].fill(length);
         }
-        let mut new_across = across.clone();
-        let mut new_down = down.clone();
-        if direction == Direction::Across {
-            new_across.remove(index);
-        } else {
-            new_down.remove(index);
+        for (pos, length) in self.down_positions() {
+            for row in &mut down[pos.1..pos.1 + length] {
+                row[pos.0] = length;
+            }
         }
-        for word in matches.iter() {
-            self.set_word(word, pos, direction);
-            if self.fill_recursive(matcher, &new_across, &new_down, rng, cache) {
-                return true;
+        self.positions()
+            .filter(|pos| self[*pos].is_open() && (across[pos.1][pos.0] < 2 || down[pos.1][pos.0] < 2))
+            .collect()
+    }
+
+    pub fn is_symmetric(&self, symmetry: Symmetry) -> bool {
+        self.asymmetric_cells(symmetry).is_empty()
+    }
+
+    pub fn asymmetric_cells(&self, symmetry: Symmetry) -> Vec<Pos> {
+        self.positions()
+            .filter(|pos| {
+                let partner = symmetry.partner(*pos, self.width, self.height);
+                (self[*pos] == Cell::Black) != (self[partner] == Cell::Black) || (self[*pos] == Cell::Void) != (self[partner] == Cell::Void)
+            })
+            .collect()
+    }
+
+    pub fn neighbours(&self, pos: Pos) -> impl Iterator<Item = Pos> {
+        let (width, height) = (self.width, self.height);
+        let Pos(x, y) = pos;
+        [
+            (x > 0).then(|| Pos(x - 1, y)),
+            (x + 1 < width).then(|| Pos(x + 1, y)),
+            (y > 0).then(|| Pos(x, y - 1)),
+            (y + 1 < height).then(|| Pos(x, y + 1)),
+        ].into_iter().flatten()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefillReport {
+    pub open_slots: Vec<Slot>,
+    pub diagnosis: Diagnosis,
+}
+
+impl Crossword {
+    pub fn is_locked(&self, pos: Pos) -> bool {
+        self.locked[pos.1][pos.0]
+    }
+
+    pub fn lock_cell(&mut self, pos: Pos) {
+        self.locked[pos.1][pos.0] = true;
+    }
+
+    pub fn unlock_cell(&mut self, pos: Pos) {
+        self.locked[pos.1][pos.0] = false;
+    }
+
+    pub fn lock_slot(&mut self, slot: Slot) {
+        for pos in slot.cells() {
+            self.lock_cell(pos);
+        }
+    }
+
+    pub fn lock_letters(&mut self) {
+        for pos in self.positions().collect::<Vec<_>>() {
+            if let Cell::Letter(_) | Cell::Rebus(_) = self[pos] {
+                self.lock_cell(pos);
             }
-            self.set_pattern(direction, pos, pattern.as_slice());
-        }
-        false
-    }
-
-    fn choice_pos(&self, matcher: &Matcher, across: &Vec<(Pos, usize)>, down: &Vec<(Pos, usize)>, cache: &mut HashMap<Vec<Option<char>>, usize>) -> Option<(Pos, usize, usize, Direction)> {
-        let mut across_best_start: Option<(Pos, usize, usize)> = None;
-        let mut across_best_score: usize = usize::MAX;
-        for (i, word) in across.iter().enumerate() {
-            let (pos, length) = *word;
-            let cells = self.get_across(pos, length);
-            let score = cache.get(&cells);
-            let score = if score.is_none() {
-                let score = matcher.count_matches(cells.as_slice());
-                cache.insert(cells, score);
-                score
-            } else {
-                *score.unwrap()
-            };
-            if across_best_start.is_none() || score < across_best_score {
-                across_best_start = Some((word.0, word.1, i));
-                across_best_score = score;
+        }
+    }
+
+    pub fn clear_region(&mut self, from: Pos, to: Pos) {
+        for y in from.1.min(to.1)..=from.1.max(to.1) {
+            for x in from.0.min(to.0)..=from.0.max(to.0) {
+                self.unlock_cell(Pos(x, y));
+                if let Cell::Letter(_) | Cell::Rebus(_) = self[Pos(x, y)] {
+                    self[Pos(x, y)] = Cell::Empty;
+                }
             }
         }
+    }
 
-        let mut down_best_start: Option<(Pos, usize, usize)> = None;
-        let mut down_best_score: usize = usize::MAX;
-        for (i, word) in down.iter().enumerate() {
-            let (pos, length) = *word;
-            let cells = self.get_down(pos, length);
-            let score = cache.get(&cells);
-            let score = if score.is_none() {
-                let score = matcher.count_matches(cells.as_slice());
-                cache.insert(cells, score);
-                score
-            } else {
-                *score.unwrap()
-            };
-            if down_best_start.is_none() || score < down_best_score {
-                down_best_start = Some((word.0, word.1, i));
-                down_best_score = score;
+    pub fn clear_unlocked(&mut self) {
+        for pos in self.positions().collect::<Vec<_>>() {
+            if !self.is_locked(pos) {
+                if let Cell::Letter(_) | Cell::Rebus(_) = self[pos] {
+                    self[pos] = Cell::Empty;
+                }
             }
         }
+    }
 
-        if across_best_score <= down_best_score {
-            across_best_start.map(|pos| (pos.0, pos.1, pos.2, Direction::Across))
-        } else {
-            down_best_start.map(|pos| (pos.0, pos.1, pos.2, Direction::Down))
+    pub fn refill(&mut self, matcher: &Matcher, rng: &mut impl Rng) -> Result<(), RefillReport> {
+        let mut attempt = self.clone();
+        attempt.clear_unlocked();
+        if attempt.fill(matcher, rng) {
+            *self = attempt;
+            return Ok(());
+        }
+        attempt.clear_unlocked();
+        let open_slots = attempt.dictionary_slots().into_iter()
+            .filter(|slot| slot.cells().any(|pos| attempt[pos] == Cell::Empty))
+            .collect::<Vec<_>>();
+        Err(RefillReport { open_slots, diagnosis: diagnose(&attempt, matcher) })
+    }
+}
+
+impl Crossword {
+    pub fn entries(&self) -> Vec<(Slot, String)> {
+        self.slots().into_iter()
+            .filter_map(|slot| {
+                let pattern = self.get_pattern(slot.direction, slot.pos, slot.length);
+                pattern.into_iter().collect::<Option<String>>().map(|word| (slot, word))
+            })
+            .collect()
+    }
+
+    pub fn average_score(&self, matcher: &Matcher) -> f64 {
+        let entries = self.entries();
+        if entries.is_empty() {
+            return 0.0;
+        }
+        entries.iter().map(|(_, word)| matcher.score(word) as f64).sum::<f64>() / entries.len() as f64
+    }
+
+    pub fn from_file(path: &str) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?.replace("\r\n", "\n");
+        let text = text.trim_start();
+        let (grid, clues) = text.split_once("\n\n").unwrap_or((text, ""));
+        let mut crossword = Self::from_str(grid);
+        for line in clues.lines().filter(|line| !line.trim().is_empty()) {
+            let (slot, clue) = parse_clue_line(line)
+                .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Invalid clue line: {}", line)))?;
+            crossword.clues.insert(slot, clue);
+        }
+        Ok(crossword)
+    }
+
+    pub fn to_file(&self, path: &str) -> std::io::Result<()> {
+        let mut text = format!("{}\n", self);
+        let mut clues = self.clues.iter().collect::<Vec<_>>();
+        clues.sort_by_key(|((pos, direction), _)| (pos.1, pos.0, *direction == Direction::Down));
+        if !clues.is_empty() {
+            text.push('\n');
+        }
+        for ((pos, direction), clue) in clues {
+            let direction = match direction {
+                Direction::Across => "across",
+                Direction::Down => "down",
+            };
+            text.push_str(&format!("{},{} {}: {}\n", pos.0, pos.1, direction, clue.replace('\\', "\\\\").replace('\n', "\\n")));
+        }
+        std::fs::write(path, text)
+    }
+
+    pub fn fill_free_cells(&mut self, rng: &mut impl Rng) {
+        for pos in self.positions().collect::<Vec<_>>() {
+            if self[pos] == Cell::Empty {
+                self[pos] = Cell::Letter(rng.gen_range('A'..='Z'));
+            }
         }
     }
 }
@@ -436,4 +1061,129 @@ impl Crossword {
 // }
 
 #[derive(Debug, Hash)]
-struct Choice(Direction, Pos, usize);
\ No newline at end of file
+struct Choice(Direction, Pos, usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes_round_trip_through_text() {
+        let mut crossword = Crossword::from_str("□@ ■ A*{c:red}\n□ □{t:theme} □");
+        crossword.attributes_mut(Pos(0, 1)).color = Some("a}b\\c:d{".to_string());
+        crossword.attributes_mut(Pos(2, 1)).tag = Some("\\}}".to_string());
+        let text = crossword.to_string();
+        let parsed = Crossword::from_str(&text);
+        assert_eq!(parsed.to_string(), text);
+        for pos in crossword.positions() {
+            assert_eq!(parsed.attributes(pos), crossword.attributes(pos));
+        }
+        assert_eq!(parsed.attributes(Pos(0, 1)).unwrap().color.as_deref(), Some("a}b\\c:d{"));
+        assert_eq!(parsed.attributes(Pos(2, 1)).unwrap().tag.as_deref(), Some("\\}}"));
+        assert_eq!(parsed.attributes(Pos(1, 1)).unwrap().tag.as_deref(), Some("theme"));
+    }
+
+    #[test]
+    fn arrow_clues_round_trip_through_text() {
+        let mut crossword = Crossword::from_str("#↴ #↓{q:Big cat: lion} #↓\n#→{q:Feline}↓ □ □\n#→ □ □");
+        crossword.set_arrow_clue(Pos(0, 2), Arrow::Right, "Set {a} or \\b}");
+        crossword.add_arrow(Pos(0, 0), Arrow::DownThenRight, "}");
+        let text = crossword.to_string();
+        let parsed = Crossword::from_str(&text);
+        assert_eq!(parsed.to_string(), text);
+        for pos in crossword.positions() {
+            assert_eq!(parsed.arrows(pos), crossword.arrows(pos));
+        }
+        assert_eq!(parsed.arrows(Pos(1, 0))[0].clue, "Big cat: lion");
+        assert_eq!(parsed.arrows(Pos(0, 2))[0].clue, "Set {a} or \\b}");
+        assert_eq!(parsed.arrow_slots(), crossword.arrow_slots());
+    }
+
+    #[test]
+    fn rebus_cells_are_locked_and_cleared() {
+        let mut crossword = Crossword::from_str("[AB]C\nD[EF]");
+        crossword.clear_region(Pos(0, 0), Pos(1, 0));
+        assert_eq!(crossword.to_string(), "□ □\nD [EF]");
+
+        let mut crossword = Crossword::from_str("[AB]C\nD[EF]");
+        crossword.lock_letters();
+        assert!(crossword.positions().all(|pos| crossword.is_locked(pos)));
+        crossword.unlock_cell(Pos(1, 1));
+        crossword.clear_unlocked();
+        assert_eq!(crossword.to_string(), "[AB] C\nD □");
+    }
+
+    #[test]
+    fn failed_refill_reports_the_conflict() {
+        let matcher = Matcher::from_words(["AB", "CD"]);
+        let mut crossword = Crossword::from_str("AB\nCD");
+        let report = crossword.refill(&matcher, &mut rand::thread_rng()).unwrap_err();
+        assert_eq!(crossword.to_string(), "A B\nC D");
+        assert_eq!(report.open_slots.len(), 4);
+        assert!(!report.diagnosis.is_consistent());
+        assert!(report.diagnosis.dead_slots.iter().all(|dead| dead.initial_matches > 0));
+        assert!(!report.diagnosis.conflict.is_empty());
+        assert!(!report.diagnosis.crossings.is_empty());
+    }
+
+    #[test]
+    fn clues_round_trip_through_file() {
+        let mut crossword = Crossword::from_str("CAT\nA■O\nBEE");
+        crossword.set_clue(Slot::new(Pos(0, 0), Direction::Across, 3), "Pet");
+        crossword.set_clue(Slot::new(Pos(0, 0), Direction::Down, 3), "Taxi: back\\slash\nand newline");
+        crossword.set_clue(Slot::new(Pos(2, 0), Direction::Down, 3), "");
+        let path = std::env::temp_dir().join(format!("crossword-clues-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        crossword.to_file(path).unwrap();
+        let loaded = Crossword::from_file(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.to_string(), crossword.to_string());
+        for slot in crossword.slots() {
+            assert_eq!(loaded.clue(slot), crossword.clue(slot));
+        }
+        assert_eq!(loaded.clue(Slot::new(Pos(0, 0), Direction::Down, 3)), Some("Taxi: back\\slash\nand newline"));
+    }
+
+    #[test]
+    fn rebus_cells_round_trip_and_expand() {
+        let crossword = Crossword::from_str("[th]E□\n■■■\nCAT");
+        let text = crossword.to_string();
+        assert_eq!(text, "[TH] E □\n■ ■ ■\nC A T");
+        assert_eq!(Crossword::from_str(&text).to_string(), text);
+        let slot = Slot::new(Pos(0, 0), Direction::Across, 3);
+        assert_eq!(crossword.get_pattern(slot.direction, slot.pos, slot.length), [Some('T'), Some('H'), Some('E'), None]);
+        assert_eq!(crossword.pattern_cells(slot), [Pos(0, 0), Pos(0, 0), Pos(1, 0), Pos(2, 0)]);
+
+        let mut crossword = Crossword::from_str("[TH]□□\n■■■");
+        crossword.set_run_policy(1, RunPolicy::Unchecked);
+        assert!(crossword.fill(&Matcher::from_words(["THEN", "TEN"]), &mut rand::thread_rng()));
+        assert_eq!(crossword.to_string(), "[TH] E N\n■ ■ ■");
+    }
+
+    #[test]
+    fn bars_round_trip_and_split_entries() {
+        let crossword = Crossword::from_str("□□|□□\n□_□□□\n□□□□");
+        let text = crossword.to_string();
+        assert_eq!(text, "□ □|□ □\n□_ □ □ □\n□ □ □ □");
+        assert_eq!(Crossword::from_str(&text).to_string(), text);
+        assert!(crossword.has_bar(Pos(1, 0), Direction::Across));
+        assert!(crossword.has_bar(Pos(0, 1), Direction::Down));
+        let slots = crossword.slots();
+        assert!(slots.contains(&Slot::new(Pos(0, 0), Direction::Across, 2)));
+        assert!(slots.contains(&Slot::new(Pos(2, 0), Direction::Across, 2)));
+        assert!(slots.contains(&Slot::new(Pos(0, 0), Direction::Down, 2)));
+        assert_eq!(crossword.run_at(Pos(0, 2), Direction::Down), Some(Slot::new(Pos(0, 2), Direction::Down, 1)));
+    }
+
+    #[test]
+    fn void_cells_round_trip_and_shape_the_grid() {
+        let mut crossword = Crossword::with_mask(3, 3, |pos| pos.0 == 1 || pos.1 == 1);
+        crossword.set_run_policy(1, RunPolicy::Unchecked);
+        let text = crossword.to_string();
+        assert_eq!(text, "· □ ·\n□ □ □\n· □ ·");
+        assert_eq!(Crossword::from_str(&text).to_string(), text);
+        assert_eq!(crossword.cell_count(), 5);
+        assert!(crossword.is_symmetric(Symmetry::Rotational));
+        assert_eq!(crossword.slots(), [Slot::new(Pos(1, 0), Direction::Down, 3), Slot::new(Pos(0, 1), Direction::Across, 3)]);
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+
+use crate::crossword::{Cell, Crossword, Pos, Slot};
+use crate::matcher::Matcher;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotReport {
+    pub slot: Slot,
+    pub pattern: Vec<Option<char>>,
+    pub initial_matches: usize,
+    pub remaining_matches: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnosis {
+    pub dead_slots: Vec<SlotReport>,
+    pub conflict: Vec<Slot>,
+    pub crossings: Vec<Pos>,
+    pub tightest: Vec<SlotReport>,
+}
+
+impl Diagnosis {
+    pub fn is_consistent(&self) -> bool {
+        self.dead_slots.is_empty()
+    }
+}
+
+struct Crossing {
+    a: usize,
+    a_index: usize,
+    b: usize,
+    b_index: usize,
+    pos: Pos,
+}
+
+pub fn diagnose(crossword: &Crossword, matcher: &Matcher) -> Diagnosis {
+    let slots = crossword.dictionary_slots().into_iter()
+        .filter(|slot| slot.cells().any(|pos| crossword[pos] == Cell::Empty))
+        .collect::<Vec<_>>();
+    let patterns = slots.iter()
+        .map(|slot| crossword.get_pattern(slot.direction, slot.pos, slot.length))
+        .collect::<Vec<_>>();
+    let domains = patterns.iter()
+        .map(|pattern| matcher.find(pattern).into_iter().map(|word| word.chars().collect::<Vec<_>>()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let crossings = crossings(crossword, &slots);
+
+    let all = vec![true; slots.len()];
+    let remaining = propagate(&domains, &crossings, &all);
+    let reports = (0..slots.len())
+        .map(|i| SlotReport {
+            slot: slots[i],
+            pattern: patterns[i].clone(),
+            initial_matches: domains[i].len(),
+            remaining_matches: remaining[i].len(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut dead_slots = reports.iter().filter(|report| report.initial_matches == 0).cloned().collect::<Vec<_>>();
+    if dead_slots.is_empty() {
+        dead_slots = reports.iter().filter(|report| report.remaining_matches == 0).cloned().collect();
+    }
+    let mut tightest = reports.clone();
+    tightest.sort_by_key(|report| report.remaining_matches);
+    tightest.truncate(5);
+
+    let mut conflict = Vec::new();
+    let mut conflict_crossings = Vec::new();
+    if !dead_slots.is_empty() {
+        let mut subset = all;
+        for i in 0..slots.len() {
+            subset[i] = false;
+            if !wipes_out(&domains, &crossings, &subset) {
+                subset[i] = true;
+            }
+        }
+        conflict = (0..slots.len()).filter(|i| subset[*i]).map(|i| slots[i]).collect();
+        conflict_crossings = crossings.iter()
+            .filter(|crossing| subset[crossing.a] && subset[crossing.b])
+            .map(|crossing| crossing.pos)
+            .collect();
+    }
+
+    Diagnosis { dead_slots, conflict, crossings: conflict_crossings, tightest }
+}
+
+fn crossings(crossword: &Crossword, slots: &[Slot]) -> Vec<Crossing> {
+    let cells = slots.iter().map(|slot| crossword.pattern_cells(*slot)).collect::<Vec<_>>();
+    let mut result = Vec::new();
+    for a in 0..slots.len() {
+        for b in a + 1..slots.len() {
+            if slots[a].direction == slots[b].direction {
+                continue;
+            }
+            for pos in slots[a].cells() {
+                let a_index = cells[a].iter().position(|other| *other == pos).unwrap();
+                if let Some(b_index) = cells[b].iter().position(|other| *other == pos) {
+                    result.push(Crossing { a, a_index, b, b_index, pos });
+                }
+            }
+        }
+    }
+    result
+}
+
+fn wipes_out(domains: &[Vec<Vec<char>>], crossings: &[Crossing], subset: &[bool]) -> bool {
+    propagate(domains, crossings, subset).iter().enumerate().any(|(i, domain)| subset[i] && domain.is_empty())
+}
+
+fn propagate(domains: &[Vec<Vec<char>>], crossings: &[Crossing], subset: &[bool]) -> Vec<Vec<Vec<char>>> {
+    let mut domains = domains.to_vec();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for crossing in crossings.iter().filter(|crossing| subset[crossing.a] && subset[crossing.b]) {
+            let a_letters = domains[crossing.a].iter().map(|word| word[crossing.a_index]).collect::<HashSet<_>>();
+            let b_letters = domains[crossing.b].iter().map(|word| word[crossing.b_index]).collect::<HashSet<_>>();
+            let a_len = domains[crossing.a].len();
+            let b_len = domains[crossing.b].len();
+            domains[crossing.a].retain(|word| b_letters.contains(&word[crossing.a_index]));
+            domains[crossing.b].retain(|word| a_letters.contains(&word[crossing.b_index]));
+            if domains[crossing.a].len() != a_len || domains[crossing.b].len() != b_len {
+                changed = true;
+            }
+            if domains[crossing.a].is_empty() || domains[crossing.b].is_empty() {
+                return domains;
+            }
+        }
+    }
+    domains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crossword::Direction;
+
+    #[test]
+    fn fillable_grid_is_consistent() {
+        let diagnosis = diagnose(&Crossword::new(2, 2), &Matcher::from_words(["AB", "BA"]));
+        assert!(diagnosis.is_consistent());
+        assert!(diagnosis.conflict.is_empty());
+        assert_eq!(diagnosis.tightest.len(), 4);
+        assert!(diagnosis.tightest.iter().all(|report| report.remaining_matches == 2));
+    }
+
+    #[test]
+    fn slot_without_matches_is_dead() {
+        let crossword = Crossword::from_str("Q□\n□□");
+        let diagnosis = diagnose(&crossword, &Matcher::from_words(["AB", "BA"]));
+        let dead = diagnosis.dead_slots.iter().map(|report| (report.slot, report.initial_matches)).collect::<Vec<_>>();
+        assert_eq!(dead, [(Slot::new(Pos(0, 0), Direction::Across, 2), 0), (Slot::new(Pos(0, 0), Direction::Down, 2), 0)]);
+        assert_eq!(diagnosis.dead_slots[0].pattern, [Some('Q'), None]);
+    }
+
+    #[test]
+    fn crossing_conflict_is_narrowed_down() {
+        let crossword = Crossword::from_str("A□■□□\n□□■□□");
+        let diagnosis = diagnose(&crossword, &Matcher::from_words(["AB", "CD", "XY", "YX"]));
+        assert!(!diagnosis.is_consistent());
+        assert!(diagnosis.dead_slots.iter().all(|report| report.initial_matches > 0 && report.remaining_matches == 0));
+        assert!(!diagnosis.conflict.is_empty());
+        assert!(diagnosis.conflict.iter().all(|slot| slot.pos.0 < 2));
+        assert!(diagnosis.crossings.iter().all(|pos| pos.0 < 2));
+        assert!(!diagnosis.crossings.is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::crossword::{Cell, Crossword, Direction, Pos, Slot};
+use crate::export::ipuz_clues;
+
+pub fn to_diagramless_text(crossword: &Crossword) -> String {
+    let numbered = crossword.numbered_slots();
+    let mut result = String::new();
+    writeln!(result, "{}x{}", crossword.width(), crossword.height()).unwrap();
+    for (title, direction) in [("Across", Direction::Across), ("Down", Direction::Down)] {
+        writeln!(result, "\n{}", title).unwrap();
+        for (number, slot) in numbered.iter().filter(|(_, slot)| slot.direction == direction) {
+            writeln!(result, "{}. {}", number, crossword.clue(*slot).unwrap_or("")).unwrap();
+        }
+    }
+    result
+}
+
+pub fn to_diagramless_ipuz(crossword: &Crossword) -> String {
+    let numbered = crossword.numbered_slots();
+    let rows = (0..crossword.height())
+        .map(|_| format!("[{}]", vec!["0"; crossword.width()].join(",")))
+        .collect::<Vec<_>>();
+    let mut result = String::new();
+    write!(result, "{{\"version\":\"http://ipuz.org/v2\",\"kind\":[\"http://ipuz.org/crossword/diagramless#1\"]").unwrap();
+    write!(result, ",\"dimensions\":{{\"width\":{},\"height\":{}}}", crossword.width(), crossword.height()).unwrap();
+    write!(result, ",\"puzzle\":[{}]", rows.join(",")).unwrap();
+    write!(
+        result,
+        ",\"clues\":{{\"Across\":[{}],\"Down\":[{}]}}}}",
+        ipuz_clues(crossword, &numbered, Direction::Across), ipuz_clues(crossword, &numbered, Direction::Down),
+    ).unwrap();
+    result
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiagramlessReport {
+    pub wrong_size: Option<(usize, usize)>,
+    pub misplaced_blocks: Vec<Pos>,
+    pub wrong_letters: Vec<Pos>,
+    pub unfilled: Vec<Pos>,
+    pub missing_entries: Vec<(usize, Slot)>,
+    pub extra_entries: Vec<(usize, Slot)>,
+}
+
+impl DiagramlessReport {
+    pub fn is_correct(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+pub fn check_diagramless(solution: &Crossword, attempt: &Crossword) -> DiagramlessReport {
+    let mut report = DiagramlessReport::default();
+    if solution.width() != attempt.width() || solution.height() != attempt.height() {
+        report.wrong_size = Some((attempt.width(), attempt.height()));
+        return report;
+    }
+    for pos in solution.positions() {
+        match (solution[pos], attempt[pos]) {
+            (expected, actual) if expected.is_open() != actual.is_open() => report.misplaced_blocks.push(pos),
+            (_, Cell::Empty) if solution[pos].is_open() => report.unfilled.push(pos),
+            (expected, actual) if expected.is_open() && expected != actual => report.wrong_letters.push(pos),
+            _ => {},
+        }
+    }
+    let expected = solution.numbered_slots();
+    let actual = attempt.numbered_slots();
+    let expected_set = expected.iter().copied().collect::<HashSet<_>>();
+    let actual_set = actual.iter().copied().collect::<HashSet<_>>();
+    report.missing_entries = expected.into_iter().filter(|entry| !actual_set.contains(entry)).collect();
+    report.extra_entries = actual.into_iter().filter(|entry| !expected_set.contains(entry)).collect();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crossword::RunPolicy;
+
+    fn solution() -> Crossword {
+        let mut crossword = Crossword::from_str("CAT■\nARE■\nTEN■");
+        crossword.set_run_policy(1, RunPolicy::Unchecked);
+        crossword.set_clue(Slot::new(Pos(0, 0), Direction::Across, 3), "Pet");
+        crossword.set_clue(Slot::new(Pos(0, 0), Direction::Down, 3), "Feline");
+        crossword
+    }
+
+    #[test]
+    fn exports_clues_without_the_block_pattern() {
+        let text = to_diagramless_text(&solution());
+        assert!(text.starts_with("4x3\n"));
+        assert!(text.contains("\nAcross\n1. Pet\n"));
+        assert!(text.contains("\nDown\n1. Feline\n"));
+        let ipuz = to_diagramless_ipuz(&solution());
+        assert!(ipuz.contains("\"puzzle\":[[0,0,0,0],[0,0,0,0],[0,0,0,0]]"));
+        assert!(!ipuz.contains("\"#\""));
+        assert!(ipuz.contains("[1,\"Pet\"]"));
+    }
+
+    #[test]
+    fn checks_a_reconstructed_grid() {
+        let solution = solution();
+        assert!(check_diagramless(&solution, &solution).is_correct());
+
+        let mut attempt = Crossword::from_str("■CAT\n■ARE\n■TE□");
+        attempt.set_run_policy(1, RunPolicy::Unchecked);
+        let report = check_diagramless(&solution, &attempt);
+        assert_eq!(report.misplaced_blocks, [Pos(0, 0), Pos(3, 0), Pos(0, 1), Pos(3, 1), Pos(0, 2), Pos(3, 2)]);
+        assert!(report.missing_entries.iter().any(|(number, slot)| *number == 1 && slot.pos == Pos(0, 0)));
+        assert!(report.extra_entries.iter().any(|(number, slot)| *number == 1 && slot.pos == Pos(1, 0)));
+
+        let attempt = Crossword::from_str("CAT■\nARE■\nTO□■");
+        let report = check_diagramless(&solution, &attempt);
+        assert_eq!(report.wrong_letters, [Pos(1, 2)]);
+        assert_eq!(report.unfilled, [Pos(2, 2)]);
+        assert!(report.misplaced_blocks.is_empty());
+
+        let report = check_diagramless(&solution, &Crossword::new(3, 3));
+        assert_eq!(report.wrong_size, Some((3, 3)));
+        assert!(!report.is_correct());
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::crossword::{Cell, Crossword};
+use crate::fill::{FillConfig, FillOutcome};
+use crate::matcher::Matcher;
+
+#[derive(Debug, Clone, Copy)]
+pub struct EnumerateOptions {
+    pub max_fills: usize,
+    pub time_limit: Duration,
+    pub restart_node_limit: Option<usize>,
+    pub fills_per_restart: usize,
+    pub obscure_score: u32,
+}
+
+impl Default for EnumerateOptions {
+    fn default() -> Self {
+        Self {
+            max_fills: 20,
+            time_limit: Duration::from_secs(10),
+            restart_node_limit: Some(50_000),
+            fills_per_restart: 1,
+            obscure_score: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RankedFill {
+    pub crossword: Crossword,
+    pub total_score: u32,
+    pub obscure_entries: usize,
+    pub letter_diversity: usize,
+}
+
+impl RankedFill {
+    pub fn new(crossword: Crossword, matcher: &Matcher, obscure_score: u32) -> Self {
+        let scores = crossword.entries().iter().map(|(_, word)| matcher.score(word)).collect::<Vec<_>>();
+        let letters = crossword.positions()
+            .filter_map(|pos| match crossword[pos] {
+                Cell::Letter(c) => Some(c),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        Self {
+            total_score: scores.iter().sum(),
+            obscure_entries: scores.iter().filter(|score| **score < obscure_score).count(),
+            letter_diversity: letters.len(),
+            crossword,
+        }
+    }
+}
+
+pub fn enumerate_fills(crossword: &Crossword, matcher: &Matcher, options: &EnumerateOptions, rng: &mut impl Rng) -> Vec<RankedFill> {
+    let start = Instant::now();
+    let mut seen = HashSet::new();
+    let mut fills = Vec::new();
+    while fills.len() < options.max_fills {
+        let remaining = match options.time_limit.checked_sub(start.elapsed()) {
+            Some(remaining) => remaining,
+            None => break,
+        };
+        let mut config = FillConfig {
+            time_limit: Some(remaining),
+            node_limit: options.restart_node_limit,
+            ..Default::default()
+        };
+        let mut found = Vec::new();
+        let outcome = crossword.clone().fill_each(matcher, &mut config, rng, |solution| {
+            found.push(solution.clone());
+            found.len() < options.fills_per_restart
+        });
+        for mut solution in found {
+            if fills.len() < options.max_fills && seen.insert(solution.to_string()) {
+                solution.fill_free_cells(rng);
+                fills.push(RankedFill::new(solution, matcher, options.obscure_score));
+            }
+        }
+        if let FillOutcome::Unfillable(_) = outcome {
+            break;
+        }
+    }
+    fills.sort_by(|a, b| {
+        b.total_score.cmp(&a.total_score)
+            .then(a.obscure_entries.cmp(&b.obscure_entries))
+            .then(b.letter_diversity.cmp(&a.letter_diversity))
+    });
+    fills
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::crossword::RunPolicy;
+
+    #[test]
+    fn free_cells_do_not_make_fills_distinct() {
+        let matcher = Matcher::from_words(["CAT", "DOG"]);
+        let mut crossword = Crossword::from_str("□□□\n■■■\n□■□");
+        crossword.set_run_policy(1, RunPolicy::Unchecked);
+        let options = EnumerateOptions { time_limit: Duration::from_millis(300), ..Default::default() };
+        let fills = enumerate_fills(&crossword, &matcher, &options, &mut StdRng::seed_from_u64(5));
+        let mut words = fills.iter().map(|fill| fill.crossword.entries()[0].1.clone()).collect::<Vec<_>>();
+        words.sort();
+        assert_eq!(words, ["CAT", "DOG"]);
+        for fill in &fills {
+            assert!(fill.crossword.positions().all(|pos| fill.crossword[pos] != Cell::Empty));
+        }
+    }
+
+    #[test]
+    fn fills_are_ranked_by_score() {
+        let matcher = Matcher::from_words(["AT", "TO", "OX", "AX", "TA", "XO"]);
+        let crossword = Crossword::new(2, 2);
+        let options = EnumerateOptions { time_limit: Duration::from_millis(300), fills_per_restart: 100, ..Default::default() };
+        let fills = enumerate_fills(&crossword, &matcher, &options, &mut StdRng::seed_from_u64(5));
+        assert!(!fills.is_empty());
+        let grids = fills.iter().map(|fill| fill.crossword.to_string()).collect::<HashSet<_>>();
+        assert_eq!(grids.len(), fills.len());
+        assert!(fills.windows(2).all(|pair| pair[0].total_score >= pair[1].total_score));
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::crossword::{Arrow, Cell, CellAttributes, Crossword, Direction, Pos, Slot};
+
+pub fn to_json(crossword: &Crossword) -> String {
+    let rows = (0..crossword.height())
+        .map(|y| {
+            let cells = (0..crossword.width()).map(|x| solution_cell(crossword[Pos(x, y)])).collect::<Vec<_>>();
+            format!("[{}]", cells.join(","))
+        })
+        .collect::<Vec<_>>();
+    let bars = crossword.positions()
+        .flat_map(|pos| [Direction::Across, Direction::Down].map(|direction| (pos, direction)))
+        .filter(|(pos, direction)| crossword.has_bar(*pos, *direction))
+        .map(|(pos, direction)| format!("{{\"x\":{},\"y\":{},\"direction\":\"{}\"}}", pos.0, pos.1, direction_name(direction)))
+        .collect::<Vec<_>>();
+    let attributes = crossword.positions()
+        .filter_map(|pos| crossword.attributes(pos).map(|attributes| (pos, attributes)))
+        .map(|(pos, attributes)| {
+            format!(
+                "{{\"x\":{},\"y\":{},\"circle\":{},\"shade\":{},\"color\":{},\"tag\":{}}}",
+                pos.0, pos.1, attributes.circle, attributes.shade, optional_string(&attributes.color), optional_string(&attributes.tag),
+            )
+        })
+        .collect::<Vec<_>>();
+    let arrows = crossword.positions()
+        .flat_map(|pos| crossword.arrows(pos).iter().map(move |entry| (pos, entry)))
+        .map(|(pos, entry)| {
+            format!("{{\"x\":{},\"y\":{},\"arrow\":\"{}\",\"clue\":{}}}", pos.0, pos.1, arrow_name(entry.arrow), json_string(&entry.clue))
+        })
+        .collect::<Vec<_>>();
+    format!(
+        "{{\"width\":{},\"height\":{},\"grid\":[{}],\"bars\":[{}],\"attributes\":[{}],\"arrows\":[{}]}}",
+        crossword.width(), crossword.height(), rows.join(","), bars.join(","), attributes.join(","), arrows.join(","),
+    )
+}
+
+pub fn to_ipuz(crossword: &Crossword) -> String {
+    let numbered = crossword.numbered_slots();
+    let numbers = numbered.iter().map(|(number, slot)| (slot.pos, *number)).collect::<HashMap<_, _>>();
+
+    let mut puzzle = Vec::new();
+    let mut solution = Vec::new();
+    for y in 0..crossword.height() {
+        let mut puzzle_row = Vec::new();
+        let mut solution_row = Vec::new();
+        for x in 0..crossword.width() {
+            let pos = Pos(x, y);
+            let cell = match crossword[pos] {
+                Cell::Black | Cell::Clue => "\"#\"".to_string(),
+                Cell::Void => "null".to_string(),
+                _ => numbers.get(&pos).copied().unwrap_or(0).to_string(),
+            };
+            let style = ipuz_style(crossword, pos).filter(|_| crossword[pos] != Cell::Void);
+            puzzle_row.push(match style {
+                Some(style) => format!("{{\"cell\":{},\"style\":{}}}", cell, style),
+                None => cell,
+            });
+            solution_row.push(match crossword[pos] {
+                Cell::Void => "null".to_string(),
+                cell => solution_cell(cell),
+            });
+        }
+        puzzle.push(format!("[{}]", puzzle_row.join(",")));
+        solution.push(format!("[{}]", solution_row.join(",")));
+    }
+
+    let clues = |direction: Direction| ipuz_clues(crossword, &numbered, direction);
+
+    let mut result = String::new();
+    write!(result, "{{\"version\":\"http://ipuz.org/v2\",\"kind\":[\"http://ipuz.org/crossword#1\"]").unwrap();
+    write!(result, ",\"dimensions\":{{\"width\":{},\"height\":{}}}", crossword.width(), crossword.height()).unwrap();
+    write!(result, ",\"puzzle\":[{}]", puzzle.join(",")).unwrap();
+    write!(result, ",\"solution\":[{}]", solution.join(",")).unwrap();
+    write!(result, ",\"clues\":{{\"Across\":[{}],\"Down\":[{}]}}}}", clues(Direction::Across), clues(Direction::Down)).unwrap();
+    result
+}
+
+pub fn ipuz_clues(crossword: &Crossword, numbered: &[(usize, Slot)], direction: Direction) -> String {
+    numbered.iter()
+        .filter(|(_, slot)| slot.direction == direction)
+        .map(|(number, slot)| format!("[{},{}]", number, json_string(crossword.clue(*slot).unwrap_or(""))))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn ipuz_style(crossword: &Crossword, pos: Pos) -> Option<String> {
+    let attributes = crossword.attributes(pos).cloned().unwrap_or_else(CellAttributes::default);
+    let mut fields = Vec::new();
+    if attributes.circle {
+        fields.push("\"shapebg\":\"circle\"".to_string());
+    }
+    if attributes.shade {
+        fields.push("\"highlight\":true".to_string());
+    }
+    if let Some(color) = &attributes.color {
+        fields.push(format!("\"color\":{}", json_string(color)));
+    }
+    if let Some(tag) = &attributes.tag {
+        fields.push(format!("\"named\":{}", json_string(tag)));
+    }
+    let barred = [(Direction::Across, 'R'), (Direction::Down, 'B')].into_iter()
+        .filter(|(direction, _)| crossword.has_bar(pos, *direction))
+        .map(|(_, side)| side)
+        .collect::<String>();
+    if !barred.is_empty() {
+        fields.push(format!("\"barred\":\"{}\"", barred));
+    }
+    (!fields.is_empty()).then(|| format!("{{{}}}", fields.join(",")))
+}
+
+fn solution_cell(cell: Cell) -> String {
+    match cell {
+        Cell::Empty => "null".to_string(),
+        Cell::Black | Cell::Clue => "\"#\"".to_string(),
+        Cell::Void => "\".\"".to_string(),
+        Cell::Letter(c) => format!("\"{}\"", c),
+        Cell::Rebus(rebus) => format!("\"{}\"", rebus),
+    }
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Across => "across",
+        Direction::Down => "down",
+    }
+}
+
+fn arrow_name(arrow: Arrow) -> &'static str {
+    match arrow {
+        Arrow::Right => "right",
+        Arrow::Down => "down",
+        Arrow::DownThenRight => "down-right",
+        Arrow::RightThenDown => "right-down",
+    }
+}
+
+fn optional_string(value: &Option<String>) -> String {
+    value.as_deref().map_or_else(|| "null".to_string(), json_string)
+}
+
+pub fn json_string(value: &str) -> String {
+    let mut result = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(result, "\\u{:04x}", c as u32).unwrap(),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
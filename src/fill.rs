@@ -0,0 +1,508 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::crossword::{Cell, Crossword, Direction, Pos, Slot};
+use crate::heuristics::{Mrv, RandomOrder, SlotSelector, SlotState, ValueOrder};
+use crate::matcher::Matcher;
+
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>,
+    parent: Option<Arc<CancelToken>>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn child(&self) -> Self {
+        Self { flag: Arc::default(), parent: Some(Arc::new(self.clone())) }
+    }
+
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed) || self.parent.as_ref().is_some_and(|parent| parent.is_cancelled())
+    }
+}
+
+pub struct Progress<'a> {
+    pub nodes: usize,
+    pub depth: usize,
+    pub best_depth: usize,
+    pub best: &'a Crossword,
+}
+
+pub type ProgressCallback<'a> = Box<dyn FnMut(&Progress) + 'a>;
+
+#[derive(Clone, Copy)]
+pub enum HiddenTarget<'a> {
+    Phrase(&'a str),
+    OneOf(&'a Matcher),
+}
+
+#[derive(Clone)]
+pub struct HiddenWord<'a> {
+    pub cells: Vec<Pos>,
+    pub target: HiddenTarget<'a>,
+}
+
+impl<'a> HiddenWord<'a> {
+    pub fn circled(crossword: &Crossword, target: HiddenTarget<'a>) -> Self {
+        let cells = crossword.circled_cells().into_iter().filter(|pos| crossword[*pos].is_open()).collect();
+        Self { cells, target }
+    }
+
+    fn phrase_pattern(&self) -> Option<Vec<Option<char>>> {
+        match self.target {
+            HiddenTarget::Phrase(phrase) => Some(phrase.chars().filter(|c| c.is_ascii_alphabetic()).map(|c| Some(c.to_ascii_uppercase())).collect()),
+            HiddenTarget::OneOf(_) => None,
+        }
+    }
+}
+
+pub struct FillConfig<'a> {
+    pub time_limit: Option<Duration>,
+    pub node_limit: Option<usize>,
+    pub cancel: Option<CancelToken>,
+    pub progress: Option<ProgressCallback<'a>>,
+    pub progress_interval: usize,
+    pub selector: &'a dyn SlotSelector,
+    pub value_order: &'a dyn ValueOrder,
+    pub cache_capacity: usize,
+    pub hidden: Vec<HiddenWord<'a>>,
+    pub preset: Vec<Slot>,
+}
+
+impl Default for FillConfig<'_> {
+    fn default() -> Self {
+        Self {
+            time_limit: None,
+            node_limit: None,
+            cancel: None,
+            progress: None,
+            progress_interval: 10_000,
+            selector: &Mrv,
+            value_order: &RandomOrder,
+            cache_capacity: 200_000,
+            hidden: Vec::new(),
+            preset: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FillStats {
+    pub nodes: usize,
+    pub best_depth: usize,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    TimeLimit,
+    NodeLimit,
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
+pub enum FillOutcome {
+    Filled(FillStats),
+    Unfillable(FillStats),
+    Stopped { reason: StopReason, stats: FillStats, best: Box<Crossword> },
+}
+
+impl FillOutcome {
+    pub fn is_filled(&self) -> bool {
+        matches!(self, FillOutcome::Filled(_))
+    }
+
+    pub fn stats(&self) -> FillStats {
+        match self {
+            FillOutcome::Filled(stats) | FillOutcome::Unfillable(stats) => *stats,
+            FillOutcome::Stopped { stats, .. } => *stats,
+        }
+    }
+}
+
+impl Crossword {
+    pub fn fill(&mut self, matcher: &Matcher, rng: &mut impl Rng) -> bool {
+        self.fill_with(matcher, &mut FillConfig::default(), rng).is_filled()
+    }
+
+    pub fn fill_with(&mut self, matcher: &Matcher, config: &mut FillConfig, rng: &mut impl Rng) -> FillOutcome {
+        let outcome = self.search(matcher, config, None, rng);
+        if outcome.is_filled() {
+            self.fill_free_cells(rng);
+        }
+        outcome
+    }
+
+    pub fn fill_each(&mut self, matcher: &Matcher, config: &mut FillConfig, rng: &mut impl Rng, mut on_solution: impl FnMut(&Crossword) -> bool) -> FillOutcome {
+        self.search(matcher, config, Some(&mut on_solution), rng)
+    }
+
+    fn search(&mut self, matcher: &Matcher, config: &mut FillConfig, on_solution: Option<&mut dyn FnMut(&Crossword) -> bool>, rng: &mut impl Rng) -> FillOutcome {
+        let original = self.clone();
+        let outcome = self.search_hidden(matcher, config, on_solution, rng);
+        if !outcome.is_filled() {
+            *self = original;
+        }
+        outcome
+    }
+
+    fn search_hidden(&mut self, matcher: &Matcher, config: &mut FillConfig, on_solution: Option<&mut dyn FnMut(&Crossword) -> bool>, rng: &mut impl Rng) -> FillOutcome {
+        let unfillable = FillOutcome::Unfillable(FillStats { nodes: 0, best_depth: 0, elapsed: Duration::ZERO });
+        if config.hidden.iter().any(|hidden| hidden.cells.iter().any(|pos| !self[*pos].is_open())) {
+            return unfillable;
+        }
+        for hidden in &config.hidden {
+            let current = self.get_cells_pattern(&hidden.cells);
+            let consistent = hidden.phrase_pattern()
+                .is_none_or(|phrase| phrase.len() == current.len() && current.iter().zip(&phrase).all(|(cell, c)| cell.is_none() || cell == c));
+            if !consistent {
+                return unfillable;
+            }
+            if let Some(phrase) = hidden.phrase_pattern() {
+                self.set_cells_pattern(&hidden.cells, &phrase);
+            }
+        }
+        if config.hidden.iter().any(|hidden| !hidden_matches(self, hidden)) {
+            return unfillable;
+        }
+        let complete_words_valid = self.dictionary_slots().into_iter()
+            .filter(|slot| !config.preset.contains(slot) && slot.cells().all(|pos| self[pos] != Cell::Empty))
+            .all(|slot| matcher.count_matches(&self.get_pattern(slot.direction, slot.pos, slot.length)) > 0);
+        if !complete_words_valid {
+            return unfillable;
+        }
+
+        let mut slots = self.dictionary_slots().into_iter()
+            .filter(|slot| slot.cells().any(|pos| self[pos] == Cell::Empty))
+            .collect::<Vec<_>>();
+        slots.sort_by_key(|slot| slot.direction == Direction::Down);
+        let mut slots_by_cell: HashMap<Pos, Vec<usize>> = HashMap::new();
+        for (i, slot) in slots.iter().enumerate() {
+            for pos in slot.cells() {
+                slots_by_cell.entry(pos).or_default().push(i);
+            }
+        }
+        let hidden_by_slot = slots.iter()
+            .map(|slot| {
+                (0..config.hidden.len())
+                    .filter(|i| matches!(config.hidden[*i].target, HiddenTarget::OneOf(_)))
+                    .filter(|i| slot.cells().any(|pos| config.hidden[*i].cells.contains(&pos)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let crossers = slots.iter().enumerate()
+            .map(|(i, slot)| slot.cells().flat_map(|pos| slots_by_cell[&pos].iter().copied()).filter(|other| *other != i).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut search = Search {
+            matcher,
+            start: Instant::now(),
+            nodes: 0,
+            best_depth: 0,
+            best: self.clone(),
+            cache: PatternCache::new(config.cache_capacity),
+            config,
+            on_solution,
+            open: vec![true; slots.len()],
+            counts: vec![0; slots.len()],
+            open_crossings: crossers.iter().map(|crossers| crossers.len()).collect(),
+            slots,
+            crossers,
+            hidden_by_slot,
+        };
+        for i in 0..search.slots.len() {
+            search.counts[i] = search.count(self, i);
+        }
+        let result = search.fill_recursive(self, 0, rng);
+        let stats = FillStats { nodes: search.nodes, best_depth: search.best_depth, elapsed: search.start.elapsed() };
+        match result {
+            SearchResult::Found => FillOutcome::Filled(stats),
+            SearchResult::Failed => FillOutcome::Unfillable(stats),
+            SearchResult::Stopped(reason) => FillOutcome::Stopped { reason, stats, best: Box::new(search.best) },
+        }
+    }
+}
+
+enum SearchResult {
+    Found,
+    Failed,
+    Stopped(StopReason),
+}
+
+struct Search<'a, 'b, 'c> {
+    matcher: &'a Matcher,
+    config: &'a mut FillConfig<'b>,
+    start: Instant,
+    nodes: usize,
+    best_depth: usize,
+    best: Crossword,
+    cache: PatternCache,
+    on_solution: Option<&'c mut dyn FnMut(&Crossword) -> bool>,
+    slots: Vec<Slot>,
+    open: Vec<bool>,
+    counts: Vec<usize>,
+    open_crossings: Vec<usize>,
+    crossers: Vec<Vec<usize>>,
+    hidden_by_slot: Vec<Vec<usize>>,
+}
+
+fn hidden_matches(crossword: &Crossword, hidden: &HiddenWord) -> bool {
+    match hidden.target {
+        HiddenTarget::Phrase(_) => true,
+        HiddenTarget::OneOf(matcher) => matcher.count_matches(&crossword.get_cells_pattern(&hidden.cells)) > 0,
+    }
+}
+
+struct PatternCache {
+    capacity: usize,
+    current: HashMap<Vec<Option<char>>, usize>,
+    previous: HashMap<Vec<Option<char>>, usize>,
+}
+
+impl PatternCache {
+    fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(2), current: HashMap::new(), previous: HashMap::new() }
+    }
+
+    fn get_or_insert_with(&mut self, pattern: Vec<Option<char>>, count: impl FnOnce(&[Option<char>]) -> usize) -> usize {
+        if let Some(count) = self.current.get(&pattern) {
+            return *count;
+        }
+        let count = match self.previous.remove(&pattern) {
+            Some(count) => count,
+            None => count(&pattern),
+        };
+        if self.current.len() >= self.capacity / 2 {
+            self.previous = std::mem::take(&mut self.current);
+        }
+        self.current.insert(pattern, count);
+        count
+    }
+}
+
+impl Search<'_, '_, '_> {
+    fn fill_recursive(&mut self, crossword: &mut Crossword, depth: usize, rng: &mut impl Rng) -> SearchResult {
+        if let Some(reason) = self.visit(crossword, depth) {
+            return SearchResult::Stopped(reason);
+        }
+        let indices = (0..self.slots.len()).filter(|i| self.open[*i]).collect::<Vec<_>>();
+        if indices.iter().any(|i| self.counts[*i] == 0) {
+            return SearchResult::Failed;
+        }
+        let states = indices.iter()
+            .map(|i| SlotState { slot: self.slots[*i], matches: self.counts[*i], open_crossings: self.open_crossings[*i] })
+            .collect::<Vec<_>>();
+        let index = match self.config.selector.select(&states) {
+            Some(choice) => indices[choice],
+            None => {
+                let saved = self.config.hidden.iter().map(|hidden| crossword.get_cells_pattern(&hidden.cells)).collect::<Vec<_>>();
+                if !self.complete_hidden(crossword, rng) {
+                    return SearchResult::Failed;
+                }
+                let keep_going = self.on_solution.as_mut().is_some_and(|on_solution| on_solution(crossword));
+                if keep_going {
+                    for (hidden, pattern) in self.config.hidden.iter().zip(saved) {
+                        crossword.set_cells_pattern(&hidden.cells, &pattern);
+                    }
+                }
+                return if keep_going { SearchResult::Failed } else { SearchResult::Found };
+            },
+        };
+        let slot = self.slots[index];
+        let pattern = crossword.get_pattern(slot.direction, slot.pos, slot.length);
+        let mut matches = self.matcher.find(&pattern);
+        self.config.value_order.order(crossword, slot, &mut matches, self.matcher, rng);
+        self.close(index);
+        let saved = self.crossers[index].iter().map(|other| self.counts[*other]).collect::<Vec<_>>();
+        let mut result = SearchResult::Failed;
+        for word in matches.iter() {
+            crossword.set_word(word, slot.pos, slot.direction);
+            if !self.hidden_by_slot[index].iter().all(|i| hidden_matches(crossword, &self.config.hidden[*i])) {
+                crossword.set_pattern(slot.direction, slot.pos, &pattern);
+                continue;
+            }
+            for other in self.crossers[index].clone() {
+                if self.open[other] {
+                    self.counts[other] = self.count(crossword, other);
+                }
+            }
+            match self.fill_recursive(crossword, depth + 1, rng) {
+                SearchResult::Failed => {},
+                found => {
+                    result = found;
+                    break;
+                },
+            }
+            crossword.set_pattern(slot.direction, slot.pos, &pattern);
+        }
+        for (other, count) in self.crossers[index].clone().into_iter().zip(saved) {
+            self.counts[other] = count;
+        }
+        self.reopen(index);
+        result
+    }
+
+    fn complete_hidden(&mut self, crossword: &mut Crossword, rng: &mut impl Rng) -> bool {
+        for hidden in &self.config.hidden {
+            let HiddenTarget::OneOf(matcher) = hidden.target else {
+                continue;
+            };
+            let pattern = crossword.get_cells_pattern(&hidden.cells);
+            if pattern.iter().all(|c| c.is_some()) {
+                continue;
+            }
+            let matches = matcher.find(&pattern);
+            if matches.is_empty() {
+                return false;
+            }
+            let word = &matches[rng.gen_range(0..matches.len())];
+            crossword.set_cells_pattern(&hidden.cells, &word.chars().map(Some).collect::<Vec<_>>());
+        }
+        true
+    }
+
+    fn close(&mut self, index: usize) {
+        self.open[index] = false;
+        for other in &self.crossers[index] {
+            self.open_crossings[*other] -= 1;
+        }
+    }
+
+    fn reopen(&mut self, index: usize) {
+        self.open[index] = true;
+        for other in &self.crossers[index] {
+            self.open_crossings[*other] += 1;
+        }
+    }
+
+    fn count(&mut self, crossword: &Crossword, index: usize) -> usize {
+        let slot = self.slots[index];
+        let pattern = crossword.get_pattern(slot.direction, slot.pos, slot.length);
+        let matcher = self.matcher;
+        self.cache.get_or_insert_with(pattern, |pattern| matcher.count_matches(pattern))
+    }
+
+    fn visit(&mut self, crossword: &Crossword, depth: usize) -> Option<StopReason> {
+        self.nodes += 1;
+        if depth > self.best_depth {
+            self.best_depth = depth;
+            self.best = crossword.clone();
+        }
+        if let Some(progress) = self.config.progress.as_mut() {
+            if self.nodes.is_multiple_of(self.config.progress_interval.max(1)) {
+                progress(&Progress { nodes: self.nodes, depth, best_depth: self.best_depth, best: &self.best });
+            }
+        }
+        if self.config.node_limit.is_some_and(|limit| self.nodes > limit) {
+            return Some(StopReason::NodeLimit);
+        }
+        if self.config.cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+            return Some(StopReason::Cancelled);
+        }
+        if self.config.time_limit.is_some_and(|limit| self.start.elapsed() > limit) {
+            return Some(StopReason::TimeLimit);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::crossword::RunPolicy;
+
+    fn matcher() -> Matcher {
+        Matcher::from_words(["CAT", "ARE", "TEN", "COT", "ORE", "TOE", "ACE", "ERA", "NET"])
+    }
+
+    #[test]
+    fn stopped_fill_restores_the_grid() {
+        let mut crossword = Crossword::new(3, 3);
+        let mut config = FillConfig { node_limit: Some(2), ..Default::default() };
+        let outcome = crossword.fill_with(&matcher(), &mut config, &mut StdRng::seed_from_u64(1));
+        let FillOutcome::Stopped { reason, best, .. } = outcome else {
+            panic!("expected the node limit to stop the fill");
+        };
+        assert_eq!(reason, StopReason::NodeLimit);
+        assert_eq!(crossword.to_string(), Crossword::new(3, 3).to_string());
+        assert!(best.positions().any(|pos| best[pos] != Cell::Empty));
+    }
+
+    #[test]
+    fn cancelled_fill_restores_the_grid() {
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let mut crossword = Crossword::new(3, 3);
+        let mut config = FillConfig { cancel: Some(cancel), ..Default::default() };
+        let outcome = crossword.fill_with(&matcher(), &mut config, &mut StdRng::seed_from_u64(1));
+        assert!(matches!(outcome, FillOutcome::Stopped { reason: StopReason::Cancelled, .. }));
+        assert_eq!(crossword.to_string(), Crossword::new(3, 3).to_string());
+    }
+
+    #[test]
+    fn filled_grid_has_only_dictionary_words_and_no_empty_cells() {
+        let matcher = matcher();
+        let mut rng = StdRng::seed_from_u64(2);
+        for template in ["□□□\n□□□\n□□□", "□□□\n■■■\n□■□", "C□□\n□□□\n□□N"] {
+            let mut crossword = Crossword::from_str(template);
+            crossword.set_run_policy(1, RunPolicy::Unchecked);
+            assert!(crossword.fill(&matcher, &mut rng), "could not fill\n{}", template);
+            assert!(crossword.positions().all(|pos| crossword[pos] != Cell::Empty));
+            for (_, word) in crossword.entries() {
+                assert_eq!(matcher.count_matches(&word.chars().map(Some).collect::<Vec<_>>()), 1, "{} is not a word", word);
+            }
+        }
+    }
+
+    #[test]
+    fn complete_non_words_are_rejected_unless_preset() {
+        let template = Crossword::from_str("QQQ\n■■■\n□□□");
+        let mut crossword = template.clone();
+        crossword.set_run_policy(1, RunPolicy::Unchecked);
+        let mut rng = StdRng::seed_from_u64(3);
+        assert!(!crossword.fill(&matcher(), &mut rng));
+        assert_eq!(crossword.to_string(), template.to_string());
+
+        let mut config = FillConfig { preset: vec![Slot::new(Pos(0, 0), Direction::Across, 3)], ..Default::default() };
+        assert!(crossword.fill_with(&matcher(), &mut config, &mut rng).is_filled());
+        assert_eq!(crossword.get_across(Pos(0, 0), 3), [Some('Q'); 3]);
+    }
+
+    #[test]
+    fn hidden_phrase_is_spelled_in_its_cells() {
+        let mut crossword = Crossword::new(3, 3);
+        let cells = vec![Pos(0, 0), Pos(1, 1), Pos(2, 2)];
+        let mut config = FillConfig { hidden: vec![HiddenWord { cells: cells.clone(), target: HiddenTarget::Phrase("c-r-n") }], ..Default::default() };
+        assert!(crossword.fill_with(&matcher(), &mut config, &mut StdRng::seed_from_u64(4)).is_filled());
+        assert_eq!(crossword.get_cells_pattern(&cells), [Some('C'), Some('R'), Some('N')]);
+    }
+
+    #[test]
+    fn hidden_word_over_a_block_is_unfillable() {
+        let template = Crossword::from_str("□@□□\n■@□□\n□□□");
+        let mut crossword = template.clone();
+        let mut config = FillConfig {
+            hidden: vec![HiddenWord { cells: vec![Pos(0, 0), Pos(0, 1)], target: HiddenTarget::Phrase("AB") }],
+            ..Default::default()
+        };
+        assert!(matches!(crossword.fill_with(&matcher(), &mut config, &mut StdRng::seed_from_u64(5)), FillOutcome::Unfillable(_)));
+        assert_eq!(crossword.to_string(), template.to_string());
+
+        let words = matcher();
+        assert_eq!(HiddenWord::circled(&crossword, HiddenTarget::OneOf(&words)).cells, [Pos(0, 0)]);
+    }
+}
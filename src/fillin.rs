@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use rand::Rng;
+
+use crate::crossword::{Cell, Crossword, Slot};
+use crate::fill::{FillConfig, FillOutcome};
+use crate::matcher::Matcher;
+use crate::unique::{Completions, CountOptions};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FillInOptions {
+    pub starter: bool,
+    pub count: CountOptions,
+}
+
+#[derive(Debug, Clone)]
+pub struct FillIn {
+    pub solution: Crossword,
+    pub template: Crossword,
+    pub entries_by_length: BTreeMap<usize, Vec<String>>,
+    pub starter: Option<(Slot, String)>,
+    pub completions: Completions,
+}
+
+impl FillIn {
+    pub fn is_unique(&self) -> bool {
+        self.completions.is_unique()
+    }
+}
+
+pub fn make_fill_in(solution: &Crossword, options: &FillInOptions, rng: &mut impl Rng) -> FillIn {
+    let entries = solution.entries();
+    let mut entries_by_length: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for (_, word) in &entries {
+        entries_by_length.entry(word.len()).or_default().push(word.clone());
+    }
+    for words in entries_by_length.values_mut() {
+        words.sort();
+    }
+
+    let mut template = solution.clone();
+    for pos in template.positions().collect::<Vec<_>>() {
+        if let Cell::Letter(_) = template[pos] {
+            template[pos] = Cell::Empty;
+        }
+    }
+    let starter = (options.starter && !entries.is_empty()).then(|| {
+        let most_ambiguous = entries.iter().map(|(_, word)| entries_by_length[&word.len()].len()).max().unwrap_or(0);
+        let candidates = entries.iter().filter(|(_, word)| entries_by_length[&word.len()].len() == most_ambiguous).collect::<Vec<_>>();
+        candidates[rng.gen_range(0..candidates.len())].clone()
+    });
+    if let Some((slot, word)) = &starter {
+        template.set_word(word, slot.pos, slot.direction);
+    }
+
+    let completions = count_fill_in(&template, &entries_by_length, &options.count, rng);
+    FillIn { solution: solution.clone(), template, entries_by_length, starter, completions }
+}
+
+pub fn count_fill_in(template: &Crossword, entries_by_length: &BTreeMap<usize, Vec<String>>, options: &CountOptions, rng: &mut impl Rng) -> Completions {
+    let expected = entries_by_length.values().flatten().cloned().collect::<Vec<_>>();
+    let matcher = Matcher::from_words(expected.iter().map(|word| word.as_str()));
+    let mut expected = expected;
+    expected.sort();
+
+    let cap = options.max_solutions.max(2);
+    let mut config = FillConfig {
+        time_limit: options.time_limit,
+        node_limit: options.node_limit,
+        ..Default::default()
+    };
+    let mut found = Vec::new();
+    let outcome = template.clone().fill_each(&matcher, &mut config, rng, |solution| {
+        let mut words = solution.entries().into_iter().map(|(_, word)| word).collect::<Vec<_>>();
+        words.sort();
+        if words == expected {
+            found.push(solution.clone());
+        }
+        found.len() < cap
+    });
+    match outcome {
+        FillOutcome::Stopped { reason, .. } => Completions::Unknown { reason, found },
+        _ => match found.len() {
+            0 => Completions::Zero,
+            1 => Completions::One(Box::new(found.pop().unwrap())),
+            _ => Completions::Many(found),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::crossword::{Direction, Pos};
+
+    fn solution() -> Crossword {
+        Crossword::from_str("CAT\nORE\nWEB")
+    }
+
+    #[test]
+    fn template_hides_the_letters_and_lists_the_entries() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = FillInOptions { starter: false, ..Default::default() };
+        let fill_in = make_fill_in(&solution(), &options, &mut rng);
+        assert!(fill_in.template.positions().all(|pos| fill_in.template[pos] == Cell::Empty));
+        assert_eq!(fill_in.entries_by_length.len(), 1);
+        assert_eq!(fill_in.entries_by_length[&3], ["ARE", "CAT", "COW", "ORE", "TEB", "WEB"]);
+        // The transposed grid uses the same entries.
+        assert!(matches!(fill_in.completions, Completions::Many(ref found) if found.len() == 2));
+        assert!(!fill_in.is_unique());
+    }
+
+    #[test]
+    fn starter_makes_the_solution_unique() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = FillInOptions { starter: true, ..Default::default() };
+        let fill_in = make_fill_in(&solution(), &options, &mut rng);
+        let (slot, word) = fill_in.starter.clone().unwrap();
+        assert!(fill_in.template.entries().contains(&(slot, word)));
+        assert!(fill_in.is_unique());
+        let Completions::One(found) = &fill_in.completions else { panic!("expected one completion") };
+        assert_eq!(found.to_string(), fill_in.solution.to_string());
+        assert!(found.entries().contains(&(Slot::new(Pos(0, 0), Direction::Across, 3), "CAT".to_string())));
+    }
+
+    #[test]
+    fn grid_without_entries_has_no_starter() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = FillInOptions { starter: true, ..Default::default() };
+        let fill_in = make_fill_in(&Crossword::from_str("■■\n■■"), &options, &mut rng);
+        assert!(fill_in.starter.is_none());
+        assert!(fill_in.entries_by_length.is_empty());
+    }
+}
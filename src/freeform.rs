@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::crossword::{Cell, Crossword, Direction, RunPolicy, Slot, Symmetry};
+use crate::matcher::Matcher;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FreeformOptions {
+    pub symmetry: Symmetry,
+    pub max_black_ratio: f64,
+    pub min_word_length: usize,
+    pub max_candidates: usize,
+    pub max_nodes: usize,
+    pub attempts: usize,
+}
+
+impl Default for FreeformOptions {
+    fn default() -> Self {
+        Self {
+            symmetry: Symmetry::None,
+            max_black_ratio: 0.2,
+            min_word_length: 2,
+            max_candidates: 50,
+            max_nodes: 20_000,
+            attempts: 5,
+        }
+    }
+}
+
+pub fn fill_freeform(crossword: &mut Crossword, matcher: &Matcher, options: &FreeformOptions, rng: &mut impl Rng) -> bool {
+    let mut search = Search { matcher, options, nodes: 0, counts: HashMap::new() };
+    let mut best: Option<(Crossword, f64)> = None;
+    for _ in 0..options.attempts {
+        let mut attempt = crossword.clone();
+        search.nodes = options.max_nodes;
+        if !search.run(&mut attempt, rng) {
+            continue;
+        }
+        attempt.fill_free_cells(rng);
+        let quality = attempt.average_score(matcher);
+        if best.as_ref().is_none_or(|(_, best_quality)| quality > *best_quality) {
+            best = Some((attempt, quality));
+        }
+    }
+    match best {
+        Some((result, _)) => {
+            *crossword = result;
+            true
+        },
+        None => false,
+    }
+}
+
+struct Search<'a> {
+    matcher: &'a Matcher,
+    options: &'a FreeformOptions,
+    nodes: usize,
+    counts: HashMap<Vec<Option<char>>, usize>,
+}
+
+impl Search<'_> {
+    fn run(&mut self, crossword: &mut Crossword, rng: &mut impl Rng) -> bool {
+        if self.nodes == 0 {
+            return false;
+        }
+        self.nodes -= 1;
+
+        let mut best: Option<(usize, Slot)> = None;
+        for slot in crossword.dictionary_slots() {
+            let pattern = crossword.get_pattern(slot.direction, slot.pos, slot.length);
+            if !pattern.contains(&None) {
+                continue;
+            }
+            let count = self.count(&pattern);
+            if best.is_none_or(|(best_count, _)| count < best_count) {
+                best = Some((count, slot));
+            }
+        }
+        let (count, slot) = match best {
+            Some(best) => best,
+            None => return true,
+        };
+
+        let pattern = crossword.get_pattern(slot.direction, slot.pos, slot.length);
+        if count > 0 {
+            let mut matches = self.matcher.find_vec_random(&pattern, rng);
+            matches.sort_by_key(|word| std::cmp::Reverse(self.matcher.score(word)));
+            matches.truncate(self.options.max_candidates);
+            for word in matches {
+                crossword.set_word(&word, slot.pos, slot.direction);
+                if self.crossings_fillable(crossword, slot) && self.run(crossword, rng) {
+                    return true;
+                }
+                crossword.set_pattern(slot.direction, slot.pos, &pattern);
+                if self.nodes == 0 {
+                    return false;
+                }
+            }
+        }
+
+        let mut cells = slot.cells().filter(|pos| crossword[*pos] == Cell::Empty).collect::<Vec<_>>();
+        cells.shuffle(rng);
+        for pos in cells {
+            let partner = self.options.symmetry.partner(pos, crossword.width(), crossword.height());
+            if crossword[partner] != Cell::Empty {
+                continue;
+            }
+            crossword[pos] = Cell::Black;
+            crossword[partner] = Cell::Black;
+            if self.blocks_allowed(crossword) && self.run(crossword, rng) {
+                return true;
+            }
+            crossword[pos] = Cell::Empty;
+            crossword[partner] = Cell::Empty;
+            if self.nodes == 0 {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn count(&mut self, pattern: &[Option<char>]) -> usize {
+        if let Some(count) = self.counts.get(pattern) {
+            return *count;
+        }
+        let count = self.matcher.count_matches(pattern);
+        self.counts.insert(pattern.to_vec(), count);
+        count
+    }
+
+    fn crossings_fillable(&mut self, crossword: &Crossword, slot: Slot) -> bool {
+        let crossing = match slot.direction {
+            Direction::Across => Direction::Down,
+            Direction::Down => Direction::Across,
+        };
+        slot.cells()
+            .filter_map(|pos| crossword.run_at(pos, crossing))
+            .filter(|run| crossword.run_policy(run.length) == RunPolicy::Dictionary)
+            .all(|run| self.count(&crossword.get_pattern(run.direction, run.pos, run.length)) > 0)
+    }
+
+    fn blocks_allowed(&mut self, crossword: &Crossword) -> bool {
+        let cells = crossword.cell_count();
+        if crossword.black_count() as f64 > self.options.max_black_ratio * cells as f64 {
+            return false;
+        }
+        if !crossword.is_connected() {
+            return false;
+        }
+        crossword.slots().into_iter().all(|slot| {
+            if slot.length < self.options.min_word_length {
+                return false;
+            }
+            crossword.run_policy(slot.length) != RunPolicy::Dictionary
+                || self.count(&crossword.get_pattern(slot.direction, slot.pos, slot.length)) > 0
+        })
+    }
+}
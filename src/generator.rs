@@ -0,0 +1,99 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::crossword::{Cell, Crossword, Pos, Symmetry};
+
+#[derive(Debug, Clone, Copy)]
+pub struct PatternOptions {
+    pub symmetry: Symmetry,
+    pub black_ratio: f64,
+    pub min_word_length: usize,
+    pub max_word_length: usize,
+    pub max_attempts: usize,
+}
+
+impl Default for PatternOptions {
+    fn default() -> Self {
+        Self {
+            symmetry: Symmetry::Rotational,
+            black_ratio: 0.16,
+            min_word_length: 3,
+            max_word_length: 15,
+            max_attempts: 100,
+        }
+    }
+}
+
+pub fn generate_pattern(width: usize, height: usize, options: &PatternOptions, rng: &mut impl Rng) -> Option<Crossword> {
+    if options.min_word_length < 2 || options.min_word_length > options.max_word_length {
+        panic!("Invalid pattern options: word length range {}..={}", options.min_word_length, options.max_word_length);
+    }
+    for _ in 0..options.max_attempts {
+        if let Some(crossword) = generate_attempt(width, height, options, rng) {
+            return Some(crossword);
+        }
+    }
+    None
+}
+
+fn generate_attempt(width: usize, height: usize, options: &PatternOptions, rng: &mut impl Rng) -> Option<Crossword> {
+    let mut crossword = Crossword::new(width, height);
+    let target = (options.black_ratio * (width * height) as f64).round() as usize;
+
+    while let Some(run) = longest_run(&crossword).filter(|run| run.len() > options.max_word_length) {
+        let mut candidates = run;
+        candidates.shuffle(rng);
+        if !candidates.into_iter().any(|pos| try_place_black(&mut crossword, pos, options)) {
+            return None;
+        }
+    }
+
+    let mut candidates = crossword.positions().collect::<Vec<_>>();
+    candidates.shuffle(rng);
+    for pos in candidates {
+        if crossword.black_count() >= target {
+            break;
+        }
+        try_place_black(&mut crossword, pos, options);
+    }
+
+    if crossword.black_count() < target || !is_valid(&crossword, options) {
+        return None;
+    }
+    Some(crossword)
+}
+
+fn try_place_black(crossword: &mut Crossword, pos: Pos, options: &PatternOptions) -> bool {
+    let partner = options.symmetry.partner(pos, crossword.width(), crossword.height());
+    if crossword[pos] == Cell::Black || crossword[partner] == Cell::Black {
+        return false;
+    }
+    crossword[pos] = Cell::Black;
+    crossword[partner] = Cell::Black;
+    if has_short_runs(crossword, options.min_word_length) || !crossword.is_connected() {
+        crossword[pos] = Cell::Empty;
+        crossword[partner] = Cell::Empty;
+        return false;
+    }
+    true
+}
+
+fn is_valid(crossword: &Crossword, options: &PatternOptions) -> bool {
+    !has_short_runs(crossword, options.min_word_length)
+        && longest_run(crossword).map_or(0, |run| run.len()) <= options.max_word_length
+        && crossword.is_connected()
+        && crossword.unchecked_cells().is_empty()
+        && crossword.is_symmetric(options.symmetry)
+}
+
+fn has_short_runs(crossword: &Crossword, min_length: usize) -> bool {
+    crossword.across_positions().values().chain(crossword.down_positions().values()).any(|length| *length < min_length)
+}
+
+fn longest_run(crossword: &Crossword) -> Option<Vec<Pos>> {
+    let across = crossword.across_positions().into_iter()
+        .map(|(pos, length)| (0..length).map(|i| Pos(pos.0 + i, pos.1)).collect::<Vec<_>>());
+    let down = crossword.down_positions().into_iter()
+        .map(|(pos, length)| (0..length).map(|i| Pos(pos.0, pos.1 + i)).collect::<Vec<_>>());
+    across.chain(down).max_by_key(|run| run.len())
+}
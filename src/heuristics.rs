@@ -0,0 +1,100 @@
+use rand::RngCore;
+use rand::seq::SliceRandom;
+
+use crate::crossword::{Crossword, Direction, RunPolicy, Slot};
+use crate::matcher::Matcher;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotState {
+    pub slot: Slot,
+    pub matches: usize,
+    pub open_crossings: usize,
+}
+
+pub trait SlotSelector: Send + Sync {
+    fn select(&self, states: &[SlotState]) -> Option<usize>;
+}
+
+pub trait ValueOrder: Send + Sync {
+    fn order(&self, crossword: &mut Crossword, slot: Slot, words: &mut Vec<String>, matcher: &Matcher, rng: &mut dyn RngCore);
+}
+
+pub struct Mrv;
+
+impl SlotSelector for Mrv {
+    fn select(&self, states: &[SlotState]) -> Option<usize> {
+        (0..states.len()).min_by_key(|i| states[*i].matches)
+    }
+}
+
+pub struct MrvDegree;
+
+impl SlotSelector for MrvDegree {
+    fn select(&self, states: &[SlotState]) -> Option<usize> {
+        (0..states.len()).min_by_key(|i| (states[*i].matches, std::cmp::Reverse(states[*i].open_crossings)))
+    }
+}
+
+pub struct LongestFirst;
+
+impl SlotSelector for LongestFirst {
+    fn select(&self, states: &[SlotState]) -> Option<usize> {
+        (0..states.len()).min_by_key(|i| (std::cmp::Reverse(states[*i].slot.length), states[*i].matches))
+    }
+}
+
+pub struct CrossingWeighted;
+
+impl SlotSelector for CrossingWeighted {
+    fn select(&self, states: &[SlotState]) -> Option<usize> {
+        let weight = |state: &SlotState| state.matches as f64 / (1 + state.open_crossings) as f64;
+        (0..states.len()).min_by(|a, b| weight(&states[*a]).total_cmp(&weight(&states[*b])))
+    }
+}
+
+pub struct RandomOrder;
+
+impl ValueOrder for RandomOrder {
+    fn order(&self, _: &mut Crossword, _: Slot, words: &mut Vec<String>, _: &Matcher, rng: &mut dyn RngCore) {
+        words.shuffle(rng);
+    }
+}
+
+pub struct ScoreOrder;
+
+impl ValueOrder for ScoreOrder {
+    fn order(&self, _: &mut Crossword, _: Slot, words: &mut Vec<String>, matcher: &Matcher, rng: &mut dyn RngCore) {
+        words.shuffle(rng);
+        words.sort_by_key(|word| std::cmp::Reverse(matcher.score(word)));
+    }
+}
+
+pub struct LeastConstraining;
+
+impl ValueOrder for LeastConstraining {
+    fn order(&self, crossword: &mut Crossword, slot: Slot, words: &mut Vec<String>, matcher: &Matcher, rng: &mut dyn RngCore) {
+        let crossing = match slot.direction {
+            Direction::Across => Direction::Down,
+            Direction::Down => Direction::Across,
+        };
+        let runs = slot.cells()
+            .filter_map(|pos| crossword.run_at(pos, crossing))
+            .filter(|run| crossword.run_policy(run.length) == RunPolicy::Dictionary)
+            .collect::<Vec<_>>();
+        let pattern = crossword.get_pattern(slot.direction, slot.pos, slot.length);
+        words.shuffle(rng);
+        let mut weighted = words.drain(..)
+            .map(|word| {
+                crossword.set_word(&word, slot.pos, slot.direction);
+                let weight = runs.iter()
+                    .map(|run| matcher.count_matches(&crossword.get_pattern(run.direction, run.pos, run.length)) as f64)
+                    .map(|count| count.ln())
+                    .sum::<f64>();
+                (word, weight)
+            })
+            .collect::<Vec<_>>();
+        crossword.set_pattern(slot.direction, slot.pos, &pattern);
+        weighted.sort_by(|a, b| b.1.total_cmp(&a.1));
+        words.extend(weighted.into_iter().map(|(word, _)| word));
+    }
+}
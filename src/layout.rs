@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::crossword::{Cell, Crossword, Direction, Pos, RunPolicy, Slot};
+
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutOptions {
+    pub attempts: usize,
+    pub background: Cell,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            attempts: 50,
+            background: Cell::Void,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub crossword: Crossword,
+    pub placed: Vec<(Slot, String)>,
+    pub unplaced: Vec<String>,
+    pub crossings: usize,
+}
+
+impl Layout {
+    pub fn area(&self) -> usize {
+        self.crossword.width() * self.crossword.height()
+    }
+}
+
+pub fn layout_words(words: &[(&str, &str)], options: &LayoutOptions, rng: &mut impl Rng) -> Option<Layout> {
+    let mut entries: Vec<(String, String)> = Vec::new();
+    let mut rejected = Vec::new();
+    for (word, clue) in words {
+        let word = word.chars().filter(|c| c.is_ascii_alphabetic()).map(|c| c.to_ascii_uppercase()).collect::<String>();
+        if word.len() >= 2 && !entries.iter().any(|(other, _)| *other == word) {
+            entries.push((word, clue.to_string()));
+        } else {
+            rejected.push(word);
+        }
+    }
+    if entries.is_empty() {
+        return None;
+    }
+
+    let mut best: Option<Layout> = None;
+    for _ in 0..options.attempts.max(1) {
+        entries.shuffle(rng);
+        entries.sort_by_key(|(word, _)| std::cmp::Reverse(word.len()));
+        let layout = Board::default().build(&entries, options, rng);
+        let better = best.as_ref().is_none_or(|best| {
+            (std::cmp::Reverse(layout.unplaced.len()), layout.crossings, std::cmp::Reverse(layout.area()))
+                > (std::cmp::Reverse(best.unplaced.len()), best.crossings, std::cmp::Reverse(best.area()))
+        });
+        if better {
+            best = Some(layout);
+        }
+    }
+    best.map(|mut layout| {
+        layout.unplaced.extend(rejected);
+        layout
+    })
+}
+
+type Candidate = ((i32, i32), Direction, usize);
+
+#[derive(Default)]
+struct Board {
+    letters: HashMap<(i32, i32), char>,
+    words: Vec<((i32, i32), Direction, String, String)>,
+    crossings: usize,
+}
+
+impl Board {
+    fn build(mut self, entries: &[(String, String)], options: &LayoutOptions, rng: &mut impl Rng) -> Layout {
+        let mut unplaced = Vec::new();
+        for (word, clue) in entries {
+            if self.words.is_empty() {
+                self.place(word, clue, (0, 0), Direction::Across);
+                continue;
+            }
+            match self.best_placement(word, rng) {
+                Some((start, direction, crossings)) => {
+                    self.crossings += crossings;
+                    self.place(word, clue, start, direction);
+                },
+                None => unplaced.push(word.clone()),
+            }
+        }
+        self.into_layout(unplaced, options)
+    }
+
+    fn place(&mut self, word: &str, clue: &str, start: (i32, i32), direction: Direction) {
+        for (i, c) in word.chars().enumerate() {
+            self.letters.insert(offset(start, direction, i as i32), c);
+        }
+        self.words.push((start, direction, word.to_string(), clue.to_string()));
+    }
+
+    fn best_placement(&self, word: &str, rng: &mut impl Rng) -> Option<Candidate> {
+        let mut best: Option<(Candidate, (usize, i64, u32))> = None;
+        for (&cell, &letter) in &self.letters {
+            for (i, c) in word.chars().enumerate() {
+                if c != letter {
+                    continue;
+                }
+                for direction in [Direction::Across, Direction::Down] {
+                    let start = offset(cell, direction, -(i as i32));
+                    let Some(crossings) = self.crossings_if_valid(word, start, direction) else {
+                        continue;
+                    };
+                    let key = (crossings, -self.area_with(word, start, direction), rng.gen());
+                    if best.as_ref().is_none_or(|(_, best)| key > *best) {
+                        best = Some(((start, direction, crossings), key));
+                    }
+                }
+            }
+        }
+        best.map(|(placement, _)| placement)
+    }
+
+    fn crossings_if_valid(&self, word: &str, start: (i32, i32), direction: Direction) -> Option<usize> {
+        let length = word.len() as i32;
+        if self.letters.contains_key(&offset(start, direction, -1)) || self.letters.contains_key(&offset(start, direction, length)) {
+            return None;
+        }
+        let mut crossings = 0;
+        for (i, c) in word.chars().enumerate() {
+            let cell = offset(start, direction, i as i32);
+            match self.letters.get(&cell) {
+                Some(existing) if *existing == c => crossings += 1,
+                Some(_) => return None,
+                None => {
+                    let (a, b) = match direction {
+                        Direction::Across => ((cell.0, cell.1 - 1), (cell.0, cell.1 + 1)),
+                        Direction::Down => ((cell.0 - 1, cell.1), (cell.0 + 1, cell.1)),
+                    };
+                    if self.letters.contains_key(&a) || self.letters.contains_key(&b) {
+                        return None;
+                    }
+                },
+            }
+        }
+        (crossings > 0 && crossings < word.len()).then_some(crossings)
+    }
+
+    fn bounds(&self) -> ((i32, i32), (i32, i32)) {
+        let xs = self.letters.keys().map(|cell| cell.0);
+        let ys = self.letters.keys().map(|cell| cell.1);
+        ((xs.clone().min().unwrap(), ys.clone().min().unwrap()), (xs.max().unwrap(), ys.max().unwrap()))
+    }
+
+    fn area_with(&self, word: &str, start: (i32, i32), direction: Direction) -> i64 {
+        let ((min_x, min_y), (max_x, max_y)) = self.bounds();
+        let end = offset(start, direction, word.len() as i32 - 1);
+        let width = max_x.max(end.0) - min_x.min(start.0) + 1;
+        let height = max_y.max(end.1) - min_y.min(start.1) + 1;
+        width as i64 * height as i64
+    }
+
+    fn into_layout(self, unplaced: Vec<String>, options: &LayoutOptions) -> Layout {
+        let ((min_x, min_y), (max_x, max_y)) = self.bounds();
+        let mut crossword = Crossword::new((max_x - min_x + 1) as usize, (max_y - min_y + 1) as usize);
+        crossword.set_run_policy(1, RunPolicy::Unchecked);
+        for pos in crossword.positions().collect::<Vec<_>>() {
+            crossword[pos] = match self.letters.get(&(pos.0 as i32 + min_x, pos.1 as i32 + min_y)) {
+                Some(c) => Cell::Letter(*c),
+                None => options.background,
+            };
+        }
+        let mut placed = Vec::new();
+        for (start, direction, word, clue) in self.words {
+            let slot = Slot::new(Pos((start.0 - min_x) as usize, (start.1 - min_y) as usize), direction, word.len());
+            crossword.set_clue(slot, &clue);
+            placed.push((slot, word));
+        }
+        Layout { crossword, placed, unplaced, crossings: self.crossings }
+    }
+}
+
+fn offset(start: (i32, i32), direction: Direction, i: i32) -> (i32, i32) {
+    match direction {
+        Direction::Across => (start.0 + i, start.1),
+        Direction::Down => (start.0, start.1 + i),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+
+    #[test]
+    fn short_and_duplicate_words_are_unplaced() {
+        let words = [("cat", "Pet"), ("tea", "Drink"), ("a", "Article"), ("area", "Region"), ("star", "Sun"), ("Cat", "Again")];
+        let layout = layout_words(&words, &LayoutOptions::default(), &mut StdRng::seed_from_u64(1)).unwrap();
+        assert_eq!(layout.placed.len() + layout.unplaced.len(), words.len());
+        assert!(layout.unplaced.contains(&"A".to_string()));
+        assert!(layout.unplaced.contains(&"CAT".to_string()));
+    }
+
+    #[test]
+    fn placed_words_spell_their_slots() {
+        let words = [("crossword", "Puzzle"), ("word", "Entry"), ("grid", "Layout"), ("clue", "Hint"), ("across", "Direction"), ("down", "Other direction")];
+        let layout = layout_words(&words, &LayoutOptions::default(), &mut StdRng::seed_from_u64(2)).unwrap();
+        assert!(layout.unplaced.is_empty());
+        assert!(layout.crossings >= layout.placed.len() - 1);
+        let entries = layout.crossword.entries().into_iter().collect::<std::collections::HashSet<_>>();
+        assert_eq!(entries, layout.placed.iter().cloned().collect());
+        for (slot, word) in &layout.placed {
+            let clue = words.iter().find(|(other, _)| other.to_uppercase() == *word).unwrap().1;
+            assert_eq!(layout.crossword.clue(*slot), Some(clue));
+        }
+    }
+}
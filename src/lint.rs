@@ -0,0 +1,171 @@
+use crate::crossword::{Arrow, Cell, Crossword, Direction, Pos, Slot, Symmetry};
+
+#[derive(Debug, Clone, Copy)]
+pub struct LintOptions {
+    pub min_word_length: usize,
+    pub symmetry: Symmetry,
+    pub max_black_ratio: f64,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            min_word_length: 3,
+            symmetry: Symmetry::Rotational,
+            max_black_ratio: 0.17,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Finding {
+    UncheckedCell(Pos),
+    ShortEntry { pos: Pos, direction: Direction, length: usize },
+    DisconnectedRegion(Vec<Pos>),
+    Asymmetry(Pos),
+    TooManyBlacks { count: usize, ratio: f64 },
+    CheaterSquare(Pos),
+    DanglingArrow { pos: Pos, arrow: Arrow },
+    OverlappingArrowEntries(Slot, Slot),
+}
+
+impl Finding {
+    pub fn positions(&self) -> Vec<Pos> {
+        match self {
+            Finding::UncheckedCell(pos) | Finding::Asymmetry(pos) | Finding::CheaterSquare(pos) => vec![*pos],
+            Finding::DanglingArrow { pos, .. } => vec![*pos],
+            Finding::OverlappingArrowEntries(a, b) => a.cells().filter(|pos| b.cells().any(|other| other == *pos)).collect(),
+            Finding::ShortEntry { pos, direction, length } => match direction {
+                Direction::Across => (0..*length).map(|i| Pos(pos.0 + i, pos.1)).collect(),
+                Direction::Down => (0..*length).map(|i| Pos(pos.0, pos.1 + i)).collect(),
+            },
+            Finding::DisconnectedRegion(region) => region.clone(),
+            Finding::TooManyBlacks { .. } => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LintReport {
+    pub black_ratio: f64,
+    pub findings: Vec<Finding>,
+}
+
+impl LintReport {
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+pub fn lint(crossword: &Crossword, options: &LintOptions) -> LintReport {
+    let mut findings = Vec::new();
+
+    for pos in crossword.unchecked_cells() {
+        findings.push(Finding::UncheckedCell(pos));
+    }
+
+    let mut short_entries = crossword.across_positions().into_iter().map(|(pos, length)| (pos, Direction::Across, length))
+        .chain(crossword.down_positions().into_iter().map(|(pos, length)| (pos, Direction::Down, length)))
+        .filter(|(_, _, length)| *length < options.min_word_length)
+        .collect::<Vec<_>>();
+    short_entries.sort_by_key(|(pos, direction, _)| (pos.1, pos.0, *direction == Direction::Down));
+    for (pos, direction, length) in short_entries {
+        findings.push(Finding::ShortEntry { pos, direction, length });
+    }
+
+    let mut regions = crossword.regions();
+    regions.sort_by_key(|region| std::cmp::Reverse(region.len()));
+    for region in regions.into_iter().skip(1) {
+        findings.push(Finding::DisconnectedRegion(region));
+    }
+
+    for pos in crossword.asymmetric_cells(options.symmetry) {
+        findings.push(Finding::Asymmetry(pos));
+    }
+
+    let count = crossword.black_count();
+    let black_ratio = count as f64 / crossword.cell_count() as f64;
+    if black_ratio > options.max_black_ratio {
+        findings.push(Finding::TooManyBlacks { count, ratio: black_ratio });
+    }
+
+    for pos in cheater_squares(crossword) {
+        findings.push(Finding::CheaterSquare(pos));
+    }
+
+    for pos in crossword.positions() {
+        for entry in crossword.arrows(pos) {
+            let start = entry.arrow.start(pos);
+            if start.0 >= crossword.width() || start.1 >= crossword.height() || !crossword[start].is_open() {
+                findings.push(Finding::DanglingArrow { pos, arrow: entry.arrow });
+            }
+        }
+    }
+
+    let arrow_slots = crossword.arrow_slots();
+    for (i, (_, _, a)) in arrow_slots.iter().enumerate() {
+        for (_, _, b) in &arrow_slots[i + 1..] {
+            if a.direction == b.direction && a.cells().any(|pos| b.cells().any(|other| other == pos)) {
+                findings.push(Finding::OverlappingArrowEntries(*a, *b));
+            }
+        }
+    }
+
+    LintReport { black_ratio, findings }
+}
+
+fn cheater_squares(crossword: &Crossword) -> Vec<Pos> {
+    let word_count = entry_count(crossword);
+    let mut crossword = crossword.clone();
+    let mut result = Vec::new();
+    for pos in crossword.positions().collect::<Vec<_>>() {
+        if crossword[pos] != Cell::Black {
+            continue;
+        }
+        crossword[pos] = Cell::Empty;
+        if entry_count(&crossword) == word_count {
+            result.push(pos);
+        }
+        crossword[pos] = Cell::Black;
+    }
+    result
+}
+
+fn entry_count(crossword: &Crossword) -> usize {
+    crossword.across_positions().values().chain(crossword.down_positions().values())
+        .filter(|length| **length >= 2)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arrow_findings(crossword: &Crossword) -> Vec<Finding> {
+        lint(crossword, &LintOptions::default()).findings.into_iter()
+            .filter(|finding| matches!(finding, Finding::DanglingArrow { .. } | Finding::OverlappingArrowEntries(..)))
+            .collect()
+    }
+
+    #[test]
+    fn well_formed_arrow_grid_has_no_arrow_findings() {
+        let crossword = Crossword::from_str("# #↓ #↓ #↓\n#→ □ □ □\n#→ □ □ □");
+        assert!(arrow_findings(&crossword).is_empty());
+    }
+
+    #[test]
+    fn dangling_and_overlapping_arrows_are_reported() {
+        let crossword = Crossword::from_str("#↴ #→ #↓ #↓\n#→ □ □ □\n#→ □ ■ #↓");
+        let findings = arrow_findings(&crossword);
+        assert!(findings.contains(&Finding::DanglingArrow { pos: Pos(0, 0), arrow: Arrow::RightThenDown }));
+        assert!(findings.contains(&Finding::DanglingArrow { pos: Pos(1, 0), arrow: Arrow::Right }));
+        assert!(findings.contains(&Finding::DanglingArrow { pos: Pos(3, 2), arrow: Arrow::Down }));
+        assert_eq!(findings.len(), 3);
+
+        let mut crossword = Crossword::from_str("# #↓ #↓\n#→ □ □\n#→ □ □");
+        crossword.add_arrow(Pos(0, 1), Arrow::Right, "Again");
+        let findings = arrow_findings(&crossword);
+        assert_eq!(findings, [Finding::OverlappingArrowEntries(Slot::new(Pos(1, 1), Direction::Across, 2), Slot::new(Pos(1, 1), Direction::Across, 2))]);
+        assert_eq!(findings[0].positions(), [Pos(1, 1), Pos(2, 1)]);
+    }
+}
@@ -3,11 +3,29 @@
 use std::{time::{Instant, Duration}, ops::Div};
 
 use crossword::Crossword;
+use fill::{FillConfig, FillOutcome};
 use matcher::Matcher;
 
 mod crossword;
 mod trie;
 mod matcher;
+mod generator;
+mod lint;
+mod freeform;
+mod theme;
+mod fill;
+mod parallel;
+mod enumerate;
+mod diagnose;
+mod heuristics;
+mod sat;
+mod unique;
+mod export;
+mod diagramless;
+mod wordsearch;
+mod fillin;
+mod layout;
+mod acrostic;
 
 fn main() {
     let mut rng = rand::thread_rng();
@@ -45,8 +63,18 @@ fn main() {
     for i in 0..count {
         let mut crossword = crossword.clone();
         let start = Instant::now();
-        let success = crossword.fill(&matcher, &mut rng);
-        if !success { panic!("Failed to fill crossword"); }
+        let mut config = FillConfig { time_limit: Some(Duration::from_secs(60)), ..Default::default() };
+        match crossword.fill_with(&matcher, &mut config, &mut rng) {
+            FillOutcome::Filled(_) => {},
+            FillOutcome::Unfillable(stats) => {
+                println!("Failed to fill crossword after {} nodes", stats.nodes);
+                return;
+            },
+            FillOutcome::Stopped { reason, stats, best } => {
+                println!("Stopped ({:?}) after {} nodes, best partial fill:\n{}", reason, stats.nodes, best);
+                return;
+            },
+        }
         let elapsed = start.elapsed();
         sum += elapsed;
         println!("\n{}", crossword);
@@ -1,20 +1,33 @@
+use std::collections::HashMap;
 use std::io::{BufRead, Result};
 
 use rand::Rng;
 
 use crate::trie::Trie;
 
+pub const DEFAULT_SCORE: u32 = 50;
+
 pub struct Matcher {
     trie: Trie<26>,
     word_count_by_length: Vec<usize>,
+    scores: HashMap<String, u32>,
 }
 
 impl Matcher {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             trie: Trie::new(),
             word_count_by_length: Vec::new(),
+            scores: HashMap::new(),
+        }
+    }
+
+    pub fn from_words<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut matcher = Self::new();
+        for word in words {
+            matcher.insert(word);
         }
+        matcher
     }
 
     pub fn from_file(path: &str) -> Result<Self> {
@@ -22,9 +35,19 @@ impl Matcher {
         let file = std::fs::File::open(path)?;
         let reader = std::io::BufReader::new(file);
         for line in reader.lines() {
-            let word = line?.to_uppercase();
-            if is_uppercase_str(&word) {
-                matcher.insert(&word);
+            let line = line?.to_uppercase();
+            let (word, score) = match line.split_once(';') {
+                Some((word, score)) => match score.trim().parse() {
+                    Ok(score) => (word.trim(), Some(score)),
+                    Err(_) => continue,
+                },
+                None => (line.as_str(), None),
+            };
+            if is_uppercase_str(word) {
+                match score {
+                    Some(score) => matcher.insert_with_score(word, score),
+                    None => matcher.insert(word),
+                }
             }
         }
         Ok(matcher)
@@ -42,6 +65,17 @@ impl Matcher {
         self.word_count_by_length[word.len()] += 1;
     }
 
+    pub fn insert_with_score(&mut self, word: &str, score: u32) {
+        self.insert(word);
+        if score != DEFAULT_SCORE {
+            self.scores.insert(word.to_uppercase(), score);
+        }
+    }
+
+    pub fn score(&self, word: &str) -> u32 {
+        self.scores.get(word).copied().unwrap_or(DEFAULT_SCORE)
+    }
+
     pub fn word_count_by_length(&self, len: usize) -> usize {
         if len >= self.word_count_by_length.len() {
             0
@@ -69,9 +103,9 @@ impl Matcher {
 }
 
 fn char_to_int(c: char) -> usize {
-    if c >= 'A' && c <= 'Z' {
+    if c.is_ascii_uppercase() {
         c as usize - 'A' as usize
-    } else if c >= 'a' && c <= 'z' {
+    } else if c.is_ascii_lowercase() {
         c as usize - 'a' as usize
     } else {
         panic!("Invalid character: {}", c);
@@ -80,7 +114,7 @@ fn char_to_int(c: char) -> usize {
 
 fn int_to_char(i: usize) -> char {
     if i < 26 {
-        (i as u8 + 'A' as u8) as char
+        (i as u8 + b'A') as char
     } else {
         panic!("Invalid integer: {}", i);
     }
@@ -0,0 +1,128 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::crossword::Crossword;
+use crate::fill::{CancelToken, FillConfig, FillOutcome, FillStats, StopReason};
+use crate::heuristics::{CrossingWeighted, LeastConstraining, Mrv, MrvDegree, RandomOrder, ScoreOrder, SlotSelector, ValueOrder};
+use crate::matcher::Matcher;
+
+const PORTFOLIO: [(&dyn SlotSelector, &dyn ValueOrder); 4] = [
+    (&Mrv, &RandomOrder),
+    (&MrvDegree, &RandomOrder),
+    (&CrossingWeighted, &ScoreOrder),
+    (&MrvDegree, &LeastConstraining),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortfolioGoal {
+    FirstSuccess,
+    BestQuality,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParallelConfig {
+    pub threads: usize,
+    pub seed: u64,
+    pub time_limit: Duration,
+    pub restart_node_limit: Option<usize>,
+    pub goal: PortfolioGoal,
+    pub vary_heuristics: bool,
+    pub cancel: Option<CancelToken>,
+}
+
+impl Default for ParallelConfig {
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            seed: 0,
+            time_limit: Duration::from_secs(60),
+            restart_node_limit: Some(100_000),
+            goal: PortfolioGoal::FirstSuccess,
+            vary_heuristics: true,
+            cancel: None,
+        }
+    }
+}
+
+struct Shared {
+    best: Option<(Crossword, f64)>,
+    deepest: Option<(Crossword, usize)>,
+    nodes: usize,
+    unfillable: bool,
+}
+
+pub fn fill_parallel(crossword: &mut Crossword, matcher: &Matcher, config: &ParallelConfig) -> FillOutcome {
+    let start = Instant::now();
+    let cancel = config.cancel.as_ref().map_or_else(CancelToken::new, |cancel| cancel.child());
+    let shared = Mutex::new(Shared { best: None, deepest: None, nodes: 0, unfillable: false });
+
+    thread::scope(|scope| {
+        for worker in 0..config.threads.max(1) {
+            let (template, cancel, shared) = (&*crossword, cancel.clone(), &shared);
+            scope.spawn(move || {
+                let mut rng = StdRng::seed_from_u64(config.seed.wrapping_add(worker as u64));
+                let (selector, value_order) = if config.vary_heuristics { PORTFOLIO[worker % PORTFOLIO.len()] } else { PORTFOLIO[0] };
+                while !cancel.is_cancelled() {
+                    let remaining = match config.time_limit.checked_sub(start.elapsed()) {
+                        Some(remaining) => remaining,
+                        None => break,
+                    };
+                    let mut attempt = template.clone();
+                    let mut fill_config = FillConfig {
+                        time_limit: Some(remaining),
+                        node_limit: config.restart_node_limit,
+                        cancel: Some(cancel.clone()),
+                        selector,
+                        value_order,
+                        ..Default::default()
+                    };
+                    let outcome = attempt.fill_with(matcher, &mut fill_config, &mut rng);
+                    let mut shared = shared.lock().unwrap();
+                    shared.nodes += outcome.stats().nodes;
+                    match outcome {
+                        FillOutcome::Filled(_) => {
+                            let quality = attempt.average_score(matcher);
+                            if shared.best.as_ref().is_none_or(|(_, best)| quality > *best) {
+                                shared.best = Some((attempt, quality));
+                            }
+                            if config.goal == PortfolioGoal::FirstSuccess {
+                                cancel.cancel();
+                            }
+                        },
+                        FillOutcome::Unfillable(_) => {
+                            shared.unfillable = true;
+                            cancel.cancel();
+                        },
+                        FillOutcome::Stopped { stats, best, .. } => {
+                            if shared.deepest.as_ref().is_none_or(|(_, depth)| stats.best_depth > *depth) {
+                                shared.deepest = Some((*best, stats.best_depth));
+                            }
+                        },
+                    }
+                }
+            });
+        }
+    });
+
+    let shared = shared.into_inner().unwrap();
+    let best_depth = shared.deepest.as_ref().map_or(0, |(_, depth)| *depth);
+    let stats = FillStats { nodes: shared.nodes, best_depth, elapsed: start.elapsed() };
+    if let Some((result, _)) = shared.best {
+        *crossword = result;
+        return FillOutcome::Filled(stats);
+    }
+    if shared.unfillable {
+        return FillOutcome::Unfillable(stats);
+    }
+    let reason = if config.cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+        StopReason::Cancelled
+    } else {
+        StopReason::TimeLimit
+    };
+    let best = shared.deepest.map_or_else(|| crossword.clone(), |(best, _)| best);
+    FillOutcome::Stopped { reason, stats, best: Box::new(best) }
+}
@@ -0,0 +1,632 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::crossword::{Cell, Crossword, Pos, Slot};
+use crate::fill::{FillOutcome, FillStats, StopReason};
+use crate::matcher::Matcher;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cnf {
+    pub num_vars: usize,
+    pub clauses: Vec<Vec<i32>>,
+}
+
+impl Cnf {
+    pub fn new_var(&mut self) -> i32 {
+        self.num_vars += 1;
+        self.num_vars as i32
+    }
+
+    pub fn add_clause(&mut self, clause: Vec<i32>) {
+        self.clauses.push(clause);
+    }
+
+    pub fn to_dimacs(&self) -> String {
+        let mut result = String::new();
+        writeln!(result, "p cnf {} {}", self.num_vars, self.clauses.len()).unwrap();
+        for clause in &self.clauses {
+            for lit in clause {
+                write!(result, "{} ", lit).unwrap();
+            }
+            writeln!(result, "0").unwrap();
+        }
+        result
+    }
+}
+
+pub fn parse_model(output: &str, num_vars: usize) -> Option<Vec<bool>> {
+    let mut model = vec![false; num_vars + 1];
+    let mut satisfiable = false;
+    for line in output.lines() {
+        let line = line.trim();
+        if line.starts_with("s ") {
+            satisfiable = line == "s SATISFIABLE";
+        } else if let Some(values) = line.strip_prefix("v ") {
+            for value in values.split_whitespace() {
+                let lit = value.parse::<i32>().ok()?;
+                if lit > 0 && (lit as usize) <= num_vars {
+                    model[lit as usize] = true;
+                }
+            }
+        }
+    }
+    satisfiable.then_some(model)
+}
+
+pub struct Encoding {
+    pub cnf: Cnf,
+    slots: Vec<(Slot, Vec<(String, i32)>)>,
+}
+
+impl Encoding {
+    pub fn new(crossword: &Crossword, matcher: &Matcher) -> Self {
+        let mut cnf = Cnf::default();
+        let slots = crossword.dictionary_slots().into_iter()
+            .map(|slot| {
+                let pattern = crossword.get_pattern(slot.direction, slot.pos, slot.length);
+                let words = matcher.find(&pattern).into_iter().map(|word| (word, cnf.new_var())).collect::<Vec<_>>();
+                (slot, words)
+            })
+            .collect::<Vec<_>>();
+
+        let mut letters: HashMap<(Pos, char), i32> = HashMap::new();
+        let mut supports: HashMap<(usize, Pos, char), Vec<i32>> = HashMap::new();
+        for (index, (slot, words)) in slots.iter().enumerate() {
+            cnf.add_clause(words.iter().map(|(_, var)| *var).collect());
+            for (word, var) in words {
+                for (pos, c) in crossword.pattern_cells(*slot).into_iter().zip(word.chars()) {
+                    if crossword[pos] != Cell::Empty {
+                        continue;
+                    }
+                    let letter = *letters.entry((pos, c)).or_insert_with(|| cnf.new_var());
+                    cnf.add_clause(vec![-var, letter]);
+                    supports.entry((index, pos, c)).or_default().push(*var);
+                }
+            }
+        }
+        for ((_, pos, c), words) in supports {
+            let mut clause = vec![-letters[&(pos, c)]];
+            clause.extend(words);
+            cnf.add_clause(clause);
+        }
+        let mut by_cell: HashMap<Pos, Vec<i32>> = HashMap::new();
+        for ((pos, _), var) in &letters {
+            by_cell.entry(*pos).or_default().push(*var);
+        }
+        for vars in by_cell.values() {
+            for i in 0..vars.len() {
+                for j in i + 1..vars.len() {
+                    cnf.add_clause(vec![-vars[i], -vars[j]]);
+                }
+            }
+        }
+        Self { cnf, slots }
+    }
+
+    pub fn decode(&self, model: &[bool], crossword: &mut Crossword) -> bool {
+        for (slot, words) in &self.slots {
+            match words.iter().find(|(_, var)| model.get(*var as usize).copied().unwrap_or(false)) {
+                Some((word, _)) => crossword.set_word(word, slot.pos, slot.direction),
+                None => return false,
+            }
+        }
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SatOptions {
+    pub max_conflicts: Option<usize>,
+    pub time_limit: Option<Duration>,
+}
+
+impl Default for SatOptions {
+    fn default() -> Self {
+        Self {
+            max_conflicts: None,
+            time_limit: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SatResult {
+    Sat(Vec<bool>),
+    Unsat,
+    Unknown(StopReason),
+}
+
+pub fn fill_sat(crossword: &mut Crossword, matcher: &Matcher, options: &SatOptions, rng: &mut impl Rng) -> FillOutcome {
+    let start = Instant::now();
+    let encoding = Encoding::new(crossword, matcher);
+    let mut solver = Solver::new(&encoding.cnf);
+    let result = solver.solve(options);
+    let stats = FillStats { nodes: solver.decisions, best_depth: 0, elapsed: start.elapsed() };
+    match result {
+        SatResult::Sat(model) => {
+            let mut attempt = crossword.clone();
+            if !encoding.decode(&model, &mut attempt) {
+                return FillOutcome::Unfillable(stats);
+            }
+            attempt.fill_free_cells(rng);
+            *crossword = attempt;
+            FillOutcome::Filled(stats)
+        },
+        SatResult::Unsat => FillOutcome::Unfillable(stats),
+        SatResult::Unknown(reason) => FillOutcome::Stopped { reason, stats, best: Box::new(crossword.clone()) },
+    }
+}
+
+type Lit = usize;
+
+fn lit(dimacs: i32) -> Lit {
+    (dimacs.unsigned_abs() as usize) * 2 + (dimacs < 0) as usize
+}
+
+fn var(lit: Lit) -> usize {
+    lit / 2
+}
+
+pub struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    watchers: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    polarity: Vec<bool>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    activity: Vec<f64>,
+    activity_inc: f64,
+    heap: Vec<usize>,
+    heap_index: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>,
+    queue_head: usize,
+    unsat: bool,
+    pub decisions: usize,
+    pub conflicts: usize,
+}
+
+impl Solver {
+    pub fn new(cnf: &Cnf) -> Self {
+        let vars = cnf.num_vars + 1;
+        let mut solver = Self {
+            clauses: Vec::new(),
+            watchers: vec![Vec::new(); vars * 2],
+            values: vec![None; vars],
+            polarity: vec![false; vars],
+            level: vec![0; vars],
+            reason: vec![None; vars],
+            activity: vec![0.0; vars],
+            activity_inc: 1.0,
+            heap: Vec::new(),
+            heap_index: vec![None; vars],
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            queue_head: 0,
+            unsat: false,
+            decisions: 0,
+            conflicts: 0,
+        };
+        for clause in &cnf.clauses {
+            let mut clause = clause.iter().map(|l| lit(*l)).collect::<Vec<_>>();
+            clause.sort_unstable();
+            clause.dedup();
+            if clause.windows(2).any(|pair| var(pair[0]) == var(pair[1])) {
+                continue;
+            }
+            solver.add_clause(clause);
+        }
+        for v in 1..vars {
+            solver.heap_insert(v);
+        }
+        solver
+    }
+
+    fn value(&self, lit: Lit) -> Option<bool> {
+        self.values[var(lit)].map(|value| value != (lit & 1 == 1))
+    }
+
+    fn add_clause(&mut self, clause: Vec<Lit>) {
+        match clause.len() {
+            0 => self.unsat = true,
+            1 => match self.value(clause[0]) {
+                Some(false) => self.unsat = true,
+                Some(true) => {},
+                None => self.enqueue(clause[0], None),
+            },
+            _ => {
+                let index = self.clauses.len();
+                self.watchers[clause[0]].push(index);
+                self.watchers[clause[1]].push(index);
+                self.clauses.push(clause);
+            },
+        }
+    }
+
+    fn enqueue(&mut self, lit: Lit, reason: Option<usize>) {
+        let v = var(lit);
+        self.values[v] = Some(lit & 1 == 0);
+        self.level[v] = self.trail_lim.len();
+        self.reason[v] = reason;
+        self.trail.push(lit);
+    }
+
+    fn propagate(&mut self) -> Option<usize> {
+        while self.queue_head < self.trail.len() {
+            let false_lit = self.trail[self.queue_head] ^ 1;
+            self.queue_head += 1;
+            let watching = std::mem::take(&mut self.watchers[false_lit]);
+            let mut kept = Vec::with_capacity(watching.len());
+            let mut conflict = None;
+            for (i, &index) in watching.iter().enumerate() {
+                if conflict.is_some() {
+                    kept.extend_from_slice(&watching[i..]);
+                    break;
+                }
+                if self.clauses[index][0] == false_lit {
+                    self.clauses[index].swap(0, 1);
+                }
+                let first = self.clauses[index][0];
+                if self.value(first) == Some(true) {
+                    kept.push(index);
+                    continue;
+                }
+                let replacement = (2..self.clauses[index].len()).find(|k| self.value(self.clauses[index][*k]) != Some(false));
+                if let Some(k) = replacement {
+                    self.clauses[index].swap(1, k);
+                    let watch = self.clauses[index][1];
+                    self.watchers[watch].push(index);
+                    continue;
+                }
+                kept.push(index);
+                if self.value(first) == Some(false) {
+                    conflict = Some(index);
+                } else {
+                    self.enqueue(first, Some(index));
+                }
+            }
+            self.watchers[false_lit] = kept;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn analyze(&mut self, mut conflict: usize) -> (Vec<Lit>, usize) {
+        let mut seen = vec![false; self.values.len()];
+        let mut learnt = vec![0];
+        let mut counter = 0;
+        let mut implied: Option<Lit> = None;
+        let mut index = self.trail.len();
+        let current = self.trail_lim.len();
+        loop {
+            for k in 0..self.clauses[conflict].len() {
+                let q = self.clauses[conflict][k];
+                if Some(q) == implied {
+                    continue;
+                }
+                let v = var(q);
+                if !seen[v] && self.level[v] > 0 {
+                    seen[v] = true;
+                    self.bump(v);
+                    if self.level[v] == current {
+                        counter += 1;
+                    } else {
+                        learnt.push(q);
+                    }
+                }
+            }
+            loop {
+                index -= 1;
+                if seen[var(self.trail[index])] {
+                    break;
+                }
+            }
+            let p = self.trail[index];
+            seen[var(p)] = false;
+            counter -= 1;
+            if counter == 0 {
+                learnt[0] = p ^ 1;
+                break;
+            }
+            implied = Some(p);
+            conflict = self.reason[var(p)].expect("implied literal without reason");
+        }
+        let mut backtrack = 0;
+        if learnt.len() > 1 {
+            let max = (1..learnt.len()).max_by_key(|i| self.level[var(learnt[*i])]).unwrap();
+            learnt.swap(1, max);
+            backtrack = self.level[var(learnt[1])];
+        }
+        self.activity_inc /= 0.95;
+        (learnt, backtrack)
+    }
+
+    fn bump(&mut self, v: usize) {
+        self.activity[v] += self.activity_inc;
+        if self.activity[v] > 1e100 {
+            for activity in self.activity.iter_mut() {
+                *activity *= 1e-100;
+            }
+            self.activity_inc *= 1e-100;
+        }
+        if let Some(index) = self.heap_index[v] {
+            self.sift_up(index);
+        }
+    }
+
+    fn heap_insert(&mut self, v: usize) {
+        if self.heap_index[v].is_none() {
+            self.heap_index[v] = Some(self.heap.len());
+            self.heap.push(v);
+            self.sift_up(self.heap.len() - 1);
+        }
+    }
+
+    fn heap_pop(&mut self) -> Option<usize> {
+        let top = *self.heap.first()?;
+        let last = self.heap.pop().unwrap();
+        self.heap_index[top] = None;
+        if !self.heap.is_empty() {
+            self.heap[0] = last;
+            self.heap_index[last] = Some(0);
+            self.sift_down(0);
+        }
+        Some(top)
+    }
+
+    fn sift_up(&mut self, mut index: usize) {
+        while index > 0 {
+            let parent = (index - 1) / 2;
+            if self.activity[self.heap[parent]] >= self.activity[self.heap[index]] {
+                break;
+            }
+            self.heap_swap(parent, index);
+            index = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut index: usize) {
+        loop {
+            let mut largest = index;
+            for child in [2 * index + 1, 2 * index + 2] {
+                if child < self.heap.len() && self.activity[self.heap[child]] > self.activity[self.heap[largest]] {
+                    largest = child;
+                }
+            }
+            if largest == index {
+                break;
+            }
+            self.heap_swap(largest, index);
+            index = largest;
+        }
+    }
+
+    fn heap_swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        self.heap_index[self.heap[a]] = Some(a);
+        self.heap_index[self.heap[b]] = Some(b);
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.trail_lim.len() <= level {
+            return;
+        }
+        let target = self.trail_lim[level];
+        for lit in self.trail.split_off(target) {
+            let v = var(lit);
+            self.polarity[v] = lit & 1 == 0;
+            self.values[v] = None;
+            self.reason[v] = None;
+            self.heap_insert(v);
+        }
+        self.trail_lim.truncate(level);
+        self.queue_head = self.trail.len();
+    }
+
+    fn decide(&mut self) -> Option<Lit> {
+        while let Some(v) = self.heap_pop() {
+            if self.values[v].is_none() {
+                return Some(v * 2 + (!self.polarity[v]) as usize);
+            }
+        }
+        None
+    }
+
+    pub fn solve(&mut self, options: &SatOptions) -> SatResult {
+        let start = Instant::now();
+        if self.unsat || self.propagate().is_some() {
+            return SatResult::Unsat;
+        }
+        let mut restart = 1;
+        let mut restart_conflicts = 0;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                self.conflicts += 1;
+                restart_conflicts += 1;
+                if self.trail_lim.is_empty() {
+                    return SatResult::Unsat;
+                }
+                let (learnt, level) = self.analyze(conflict);
+                self.backtrack(level);
+                if learnt.len() == 1 {
+                    self.enqueue(learnt[0], None);
+                } else {
+                    let index = self.clauses.len();
+                    self.watchers[learnt[0]].push(index);
+                    self.watchers[learnt[1]].push(index);
+                    let asserting = learnt[0];
+                    self.clauses.push(learnt);
+                    self.enqueue(asserting, Some(index));
+                }
+                continue;
+            }
+            if options.max_conflicts.is_some_and(|limit| self.conflicts >= limit) {
+                return SatResult::Unknown(StopReason::NodeLimit);
+            }
+            if options.time_limit.is_some_and(|limit| start.elapsed() > limit) {
+                return SatResult::Unknown(StopReason::TimeLimit);
+            }
+            if restart_conflicts >= 100 * luby(restart) {
+                restart += 1;
+                restart_conflicts = 0;
+                self.backtrack(0);
+            }
+            match self.decide() {
+                Some(lit) => {
+                    self.decisions += 1;
+                    self.trail_lim.push(self.trail.len());
+                    self.enqueue(lit, None);
+                },
+                None => {
+                    let model = self.values.iter().map(|value| value.unwrap_or(false)).collect();
+                    return SatResult::Sat(model);
+                },
+            }
+        }
+    }
+}
+
+fn luby(mut i: usize) -> usize {
+    let mut size = 1;
+    let mut power = 1;
+    while size < i + 1 {
+        size = 2 * size + 1;
+        power *= 2;
+    }
+    while size - 1 != i {
+        size = (size - 1) / 2;
+        power /= 2;
+        i %= size;
+    }
+    power
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::crossword::RunPolicy;
+
+    const WORDS: &str = "AN AS AT BE BY DO GO HE IF IN IS IT ME NO OF ON OR SO TO UP US WE \
+        ACE ANT APE ARE ART BAT BEE CAT DOG EAR EAT EGG END ERA ICE INK NET NOT ONE ORE OWL RAT SEA SET TEA TEN TOE \
+        AREA BEAR BEAT CART DATE EAST GATE IDEA NEAT NOTE RATE REST SEAT STAR TEAR TONE";
+
+    fn matcher() -> Matcher {
+        Matcher::from_words(WORDS.split_whitespace())
+    }
+
+    fn random_grid(rng: &mut impl Rng) -> Crossword {
+        let size = rng.gen_range(3..=4);
+        let mut crossword = Crossword::new(size, size);
+        crossword.set_run_policy(1, RunPolicy::Unchecked);
+        for pos in crossword.positions().collect::<Vec<_>>() {
+            if rng.gen_bool(0.2) {
+                crossword[pos] = Cell::Black;
+            } else if rng.gen_bool(0.1) {
+                crossword[pos] = Cell::Letter("AENORST".chars().nth(rng.gen_range(0..7)).unwrap());
+            }
+        }
+        crossword
+    }
+
+    fn parse_dimacs(text: &str) -> Cnf {
+        let mut cnf = Cnf::default();
+        for line in text.lines() {
+            if let Some(header) = line.strip_prefix("p cnf ") {
+                cnf.num_vars = header.split_whitespace().next().unwrap().parse().unwrap();
+            } else {
+                let mut clause = line.split_whitespace().map(|lit| lit.parse::<i32>().unwrap()).collect::<Vec<_>>();
+                assert_eq!(clause.pop(), Some(0));
+                cnf.add_clause(clause);
+            }
+        }
+        cnf
+    }
+
+    #[test]
+    fn agrees_with_backtracking_fill() {
+        let matcher = matcher();
+        let mut rng = StdRng::seed_from_u64(7);
+        let (mut filled, mut unfillable) = (0, 0);
+        for _ in 0..300 {
+            let crossword = random_grid(&mut rng);
+            let mut backtracking = crossword.clone();
+            let expected = backtracking.fill(&matcher, &mut rng);
+            let mut sat = crossword.clone();
+            let outcome = fill_sat(&mut sat, &matcher, &SatOptions::default(), &mut rng);
+            assert!(!matches!(outcome, FillOutcome::Stopped { .. }), "stopped on\n{}", crossword);
+            assert_eq!(outcome.is_filled(), expected, "disagreement on\n{}", crossword);
+            if expected {
+                filled += 1;
+                assert!(sat.positions().all(|pos| sat[pos] != Cell::Empty), "empty cells left in\n{}", sat);
+            } else {
+                unfillable += 1;
+                assert_eq!(sat.to_string(), crossword.to_string());
+            }
+        }
+        assert!(filled > 0 && unfillable > 0);
+    }
+
+    #[test]
+    fn decoded_fill_uses_dictionary_words() {
+        let matcher = matcher();
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut checked = 0;
+        for _ in 0..100 {
+            let crossword = random_grid(&mut rng);
+            let mut sat = crossword.clone();
+            if !fill_sat(&mut sat, &matcher, &SatOptions::default(), &mut rng).is_filled() {
+                continue;
+            }
+            for pos in crossword.positions().filter(|pos| crossword[*pos] != Cell::Empty) {
+                assert_eq!(sat[pos], crossword[pos]);
+            }
+            for slot in sat.dictionary_slots() {
+                let pattern = sat.get_pattern(slot.direction, slot.pos, slot.length);
+                assert!(pattern.iter().all(|c| c.is_some()), "incomplete slot in\n{}", sat);
+                assert_eq!(matcher.count_matches(&pattern), 1, "non-word in\n{}", sat);
+            }
+            checked += 1;
+        }
+        assert!(checked > 0);
+    }
+
+    #[test]
+    fn dimacs_and_model_round_trip() {
+        let mut crossword = Crossword::from_str("□□□\n□■□\n□□□");
+        crossword.set_run_policy(1, RunPolicy::Unchecked);
+        let encoding = Encoding::new(&crossword, &matcher());
+        let text = encoding.cnf.to_dimacs();
+        assert!(text.starts_with(&format!("p cnf {} {}\n", encoding.cnf.num_vars, encoding.cnf.clauses.len())));
+        assert_eq!(parse_dimacs(&text), encoding.cnf);
+
+        let SatResult::Sat(model) = Solver::new(&encoding.cnf).solve(&SatOptions::default()) else {
+            panic!("expected a model");
+        };
+        let values = (1..=encoding.cnf.num_vars).map(|v| if model[v] { v as i32 } else { -(v as i32) }).collect::<Vec<_>>();
+        for clause in &encoding.cnf.clauses {
+            assert!(clause.iter().any(|lit| values.contains(lit)));
+        }
+        let output = format!(
+            "c solver output\ns SATISFIABLE\nv {}\nv 0\n",
+            values.iter().map(|lit| lit.to_string()).collect::<Vec<_>>().join(" "),
+        );
+        assert_eq!(parse_model(&output, encoding.cnf.num_vars), Some(model));
+        assert_eq!(parse_model("s UNSATISFIABLE\n", encoding.cnf.num_vars), None);
+    }
+
+    #[test]
+    fn complete_non_word_is_unsat() {
+        let mut crossword = Crossword::from_str("QQQ\n■■■\n□□□");
+        crossword.set_run_policy(1, RunPolicy::Unchecked);
+        let mut rng = StdRng::seed_from_u64(3);
+        assert!(matches!(fill_sat(&mut crossword, &matcher(), &SatOptions::default(), &mut rng), FillOutcome::Unfillable(_)));
+    }
+}
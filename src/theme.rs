@@ -0,0 +1,147 @@
+use rand::Rng;
+
+use crate::crossword::{Crossword, Slot, Symmetry};
+use crate::fill::FillConfig;
+use crate::matcher::Matcher;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ThemeOptions {
+    pub symmetry: Symmetry,
+    pub max_placements: usize,
+}
+
+impl Default for ThemeOptions {
+    fn default() -> Self {
+        Self {
+            symmetry: Symmetry::Rotational,
+            max_placements: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub word: String,
+    pub slot: Slot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThemeOutcome {
+    Filled(Vec<Placement>),
+    NoPlacement,
+    Unfillable { placements_tried: usize },
+}
+
+pub fn fill_with_theme(crossword: &mut Crossword, theme: &[&str], matcher: &Matcher, options: &ThemeOptions, rng: &mut impl Rng) -> ThemeOutcome {
+    let mut words = theme.iter().map(|word| word.to_uppercase()).collect::<Vec<_>>();
+    words.sort_by_key(|word| std::cmp::Reverse(word.len()));
+    let slots = crossword.slots();
+    let mut search = PlacementSearch {
+        crossword,
+        matcher,
+        options,
+        words: &words,
+        slots: &slots,
+        used: Vec::new(),
+        tried: 0,
+    };
+    match search.place(0, rng) {
+        Some(placements) => ThemeOutcome::Filled(placements),
+        None if search.tried == 0 => ThemeOutcome::NoPlacement,
+        None => ThemeOutcome::Unfillable { placements_tried: search.tried },
+    }
+}
+
+pub fn candidate_slots(crossword: &Crossword, word: &str) -> Vec<Slot> {
+    crossword.slots().into_iter().filter(|slot| fits(crossword, word, *slot)).collect()
+}
+
+fn fits(crossword: &Crossword, word: &str, slot: Slot) -> bool {
+    let pattern = crossword.get_pattern(slot.direction, slot.pos, slot.length);
+    pattern.len() == word.chars().count()
+        && pattern.iter().zip(word.chars()).all(|(cell, c)| cell.is_none_or(|cell| cell == c))
+}
+
+struct PlacementSearch<'a> {
+    crossword: &'a mut Crossword,
+    matcher: &'a Matcher,
+    options: &'a ThemeOptions,
+    words: &'a [String],
+    slots: &'a [Slot],
+    used: Vec<Placement>,
+    tried: usize,
+}
+
+impl PlacementSearch<'_> {
+    fn place(&mut self, index: usize, rng: &mut impl Rng) -> Option<Vec<Placement>> {
+        if self.tried >= self.options.max_placements {
+            return None;
+        }
+        if self.unpaired() > self.words.len() - index {
+            return None;
+        }
+        if index == self.words.len() {
+            self.tried += 1;
+            let mut attempt = self.crossword.clone();
+            let mut config = FillConfig {
+                preset: self.used.iter().map(|placement| placement.slot).collect(),
+                ..Default::default()
+            };
+            if attempt.fill_with(self.matcher, &mut config, rng).is_filled() {
+                *self.crossword = attempt;
+                return Some(self.used.clone());
+            }
+            return None;
+        }
+        let word = &self.words[index];
+        for slot in self.slots.iter().copied() {
+            if self.used.iter().any(|placement| placement.slot == slot) || !fits(self.crossword, word, slot) {
+                continue;
+            }
+            let pattern = self.crossword.get_pattern(slot.direction, slot.pos, slot.length);
+            self.crossword.set_word(word, slot.pos, slot.direction);
+            self.used.push(Placement { word: word.clone(), slot });
+            if let Some(placements) = self.place(index + 1, rng) {
+                return Some(placements);
+            }
+            self.used.pop();
+            self.crossword.set_pattern(slot.direction, slot.pos, &pattern);
+        }
+        None
+    }
+
+    fn unpaired(&self) -> usize {
+        let (width, height) = (self.crossword.width(), self.crossword.height());
+        self.used.iter()
+            .filter(|placement| {
+                let partner = self.options.symmetry.partner_slot(placement.slot, width, height);
+                !self.used.iter().any(|other| other.slot == partner)
+            })
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::crossword::{Cell, RunPolicy};
+
+    #[test]
+    fn theme_words_need_not_be_in_the_dictionary() {
+        let matcher = Matcher::from_words(["FINDS", "CLUES"]);
+        let mut crossword = Crossword::from_str("□□□□□\n■■■■■\n□□□□□");
+        crossword.set_run_policy(1, RunPolicy::Unchecked);
+        let options = ThemeOptions { symmetry: Symmetry::None, ..Default::default() };
+        let outcome = fill_with_theme(&mut crossword, &["qxzqj"], &matcher, &options, &mut StdRng::seed_from_u64(1));
+        let ThemeOutcome::Filled(placements) = outcome else {
+            panic!("expected a fill, got {:?}", outcome);
+        };
+        assert_eq!(placements.len(), 1);
+        let slot = placements[0].slot;
+        assert_eq!(crossword.get_pattern(slot.direction, slot.pos, slot.length).into_iter().collect::<Option<String>>().unwrap(), "QXZQJ");
+        assert!(crossword.positions().all(|pos| crossword[pos] != Cell::Empty));
+    }
+}
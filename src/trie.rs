@@ -21,7 +21,7 @@ impl<const ALPHABET_SIZE: usize> Trie<ALPHABET_SIZE> {
     pub fn insert(&mut self, word: &[usize]) {
         let mut node = self;
         for c in word {
-            if let None = node.children[*c] {
+            if node.children[*c].is_none() {
                 node.children[*c] = Some(Box::new(Trie::new()));
             }
             node = node.children[*c].as_mut().unwrap();
@@ -31,7 +31,7 @@ impl<const ALPHABET_SIZE: usize> Trie<ALPHABET_SIZE> {
 
     pub fn find(&self, word: &[Option<usize>]) -> Vec<Vec<usize>> {
         let mut result = self.find_reverse(word);
-        for mut word in result {
+        for word in result.iter_mut() {
             word.reverse();
         }
         result
@@ -72,7 +72,7 @@ impl<const ALPHABET_SIZE: usize> Trie<ALPHABET_SIZE> {
 
     pub fn find_random(&self, word: &[Option<usize>], rng: &mut impl Rng) -> Vec<Vec<usize>> {
         let mut result = self.find_reverse_random(word, rng);
-        for mut word in result {
+        for word in result.iter_mut() {
             word.reverse();
         }
         result
@@ -154,7 +154,7 @@ impl<const ALPHABET_SIZE: usize> Trie<ALPHABET_SIZE> {
                     let mut child_result = child.find_one_reverse_random(&word[1..], rng);
                     if let Some(word) = child_result.as_mut() {
                         word.push(c);
-                        return Some(*word);
+                        return child_result;
                     }
                 }
             }
@@ -163,7 +163,7 @@ impl<const ALPHABET_SIZE: usize> Trie<ALPHABET_SIZE> {
                     let mut child_result = child.find_one_reverse_random(&word[1..], rng);
                     if let Some(word) = child_result.as_mut() {
                         word.push(c);
-                        return Some(*word);
+                        return child_result;
                     }
                 }
             }
@@ -183,10 +183,8 @@ impl<const ALPHABET_SIZE: usize> Trie<ALPHABET_SIZE> {
             }
         } else {
             let mut result: usize = 0;
-            for child in self.children.iter() {
-                if let Some(child) = child {
-                    result += child.as_ref().count_matches(&word[1..]);
-                }
+            for child in self.children.iter().flatten() {
+                result += child.as_ref().count_matches(&word[1..]);
             }
             result
         }
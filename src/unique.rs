@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::crossword::Crossword;
+use crate::fill::{FillConfig, FillOutcome, StopReason};
+use crate::matcher::Matcher;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CountOptions {
+    pub max_solutions: usize,
+    pub time_limit: Option<Duration>,
+    pub node_limit: Option<usize>,
+}
+
+impl Default for CountOptions {
+    fn default() -> Self {
+        Self {
+            max_solutions: 2,
+            time_limit: Some(Duration::from_secs(60)),
+            node_limit: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Completions {
+    Zero,
+    One(Box<Crossword>),
+    Many(Vec<Crossword>),
+    Unknown { reason: StopReason, found: Vec<Crossword> },
+}
+
+impl Completions {
+    pub fn is_unique(&self) -> bool {
+        matches!(self, Completions::One(_))
+    }
+
+    pub fn solutions(&self) -> &[Crossword] {
+        match self {
+            Completions::Zero => &[],
+            Completions::One(solution) => std::slice::from_ref(solution.as_ref()),
+            Completions::Many(solutions) | Completions::Unknown { found: solutions, .. } => solutions,
+        }
+    }
+}
+
+pub fn count_completions(crossword: &Crossword, matcher: &Matcher, options: &CountOptions, rng: &mut impl Rng) -> Completions {
+    let cap = options.max_solutions.max(2);
+    let mut config = FillConfig {
+        time_limit: options.time_limit,
+        node_limit: options.node_limit,
+        ..Default::default()
+    };
+    let mut found = Vec::new();
+    let outcome = crossword.clone().fill_each(matcher, &mut config, rng, |solution| {
+        found.push(solution.clone());
+        found.len() < cap
+    });
+    match outcome {
+        FillOutcome::Stopped { reason, .. } => Completions::Unknown { reason, found },
+        _ => match found.len() {
+            0 => Completions::Zero,
+            1 => Completions::One(Box::new(found.pop().unwrap())),
+            _ => Completions::Many(found),
+        },
+    }
+}
+
+impl Crossword {
+    pub fn has_unique_completion(&self, matcher: &Matcher, rng: &mut impl Rng) -> bool {
+        count_completions(self, matcher, &CountOptions::default(), rng).is_unique()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+
+    fn count(template: &str, options: &CountOptions) -> Completions {
+        let matcher = Matcher::from_words(["AB", "BA"]);
+        count_completions(&Crossword::from_str(template), &matcher, options, &mut StdRng::seed_from_u64(1))
+    }
+
+    #[test]
+    fn completions_are_counted() {
+        let options = CountOptions::default();
+        assert!(matches!(count("Q□\n□□", &options), Completions::Zero));
+        let unique = count("A□\n□□", &options);
+        assert!(unique.is_unique());
+        assert_eq!(unique.solutions()[0].to_string(), "A B\nB A");
+        let Completions::Many(solutions) = count("□□\n□□", &options) else {
+            panic!("expected several completions");
+        };
+        assert_eq!(solutions.len(), 2);
+        assert_ne!(solutions[0].to_string(), solutions[1].to_string());
+    }
+
+    #[test]
+    fn complete_grids_are_checked() {
+        let options = CountOptions::default();
+        assert!(count("AB\nBA", &options).is_unique());
+        assert!(matches!(count("AB\nBB", &options), Completions::Zero));
+    }
+
+    #[test]
+    fn limits_give_an_unknown_count() {
+        let options = CountOptions { node_limit: Some(1), ..Default::default() };
+        assert!(matches!(count("□□\n□□", &options), Completions::Unknown { reason: StopReason::NodeLimit, .. }));
+    }
+}
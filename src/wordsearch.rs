@@ -0,0 +1,243 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::crossword::{Cell, Crossword, Pos};
+use crate::matcher::Matcher;
+
+pub const STEPS: [(isize, isize); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (-1, -1), (1, -1), (-1, 1)];
+
+#[derive(Debug, Clone)]
+pub struct WordSearchOptions {
+    pub steps: Vec<(isize, isize)>,
+    pub allow_overlap: bool,
+    pub key_phrase: Option<String>,
+    pub max_attempts: usize,
+    pub max_nodes: usize,
+}
+
+impl Default for WordSearchOptions {
+    fn default() -> Self {
+        Self {
+            steps: STEPS.to_vec(),
+            allow_overlap: true,
+            key_phrase: None,
+            max_attempts: 20,
+            max_nodes: 100_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HiddenPlacement {
+    pub word: String,
+    pub pos: Pos,
+    pub step: (isize, isize),
+}
+
+impl HiddenPlacement {
+    pub fn cells(&self) -> Vec<Pos> {
+        line(self.pos, self.step, self.word.len())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WordSearch {
+    pub crossword: Crossword,
+    pub placements: Vec<HiddenPlacement>,
+    pub key_cells: Vec<Pos>,
+}
+
+pub fn generate_word_search(width: usize, height: usize, words: &[&str], options: &WordSearchOptions, rng: &mut impl Rng) -> Option<WordSearch> {
+    let mut words = words.iter()
+        .map(|word| word.chars().filter(|c| c.is_ascii_alphabetic()).map(|c| c.to_ascii_uppercase()).collect::<String>())
+        .collect::<Vec<_>>();
+    words.sort_by_key(|word| std::cmp::Reverse(word.len()));
+    let nested = words.iter().enumerate().any(|(i, word)| {
+        let reversed = word.chars().rev().collect::<String>();
+        words.iter().enumerate().any(|(j, other)| i != j && (other.contains(word.as_str()) || other.contains(&reversed)))
+    });
+    if nested {
+        return None;
+    }
+    let key = options.key_phrase.as_ref()
+        .map(|phrase| phrase.chars().filter(|c| c.is_ascii_alphabetic()).map(|c| c.to_ascii_uppercase()).collect::<Vec<_>>());
+    let matcher = Matcher::from_words(words.iter().map(|word| word.as_str()));
+
+    for _ in 0..options.max_attempts {
+        let mut search = Placer {
+            crossword: Crossword::new(width, height),
+            options,
+            words: &words,
+            placements: Vec::new(),
+            nodes: 0,
+            free_cells: key.as_ref().map(|key| key.len()),
+        };
+        if !search.place(0, rng) {
+            continue;
+        }
+        let Placer { mut crossword, placements, .. } = search;
+        let key_cells = crossword.positions().filter(|pos| crossword[*pos] == Cell::Empty).collect::<Vec<_>>();
+        for _ in 0..options.max_attempts {
+            for (i, pos) in key_cells.iter().enumerate() {
+                crossword[*pos] = Cell::Letter(match &key {
+                    Some(key) => key[i],
+                    None => rng.gen_range('A'..='Z'),
+                });
+            }
+            if unintended_occurrences(&crossword, &placements, &matcher, options).is_empty() {
+                return Some(WordSearch { crossword, placements, key_cells });
+            }
+            if key.is_some() {
+                break;
+            }
+        }
+    }
+    None
+}
+
+pub fn unintended_occurrences(crossword: &Crossword, placements: &[HiddenPlacement], matcher: &Matcher, options: &WordSearchOptions) -> Vec<HiddenPlacement> {
+    let max_length = placements.iter().map(|placement| placement.word.len()).max().unwrap_or(0);
+    let placed = placements.iter().map(|placement| placement.cells()).collect::<Vec<_>>();
+    let mut result = Vec::new();
+    for pos in crossword.positions() {
+        for step in &options.steps {
+            let mut pattern = Vec::new();
+            for cell in line(pos, *step, max_length) {
+                if cell.0 >= crossword.width() || cell.1 >= crossword.height() {
+                    break;
+                }
+                match crossword[cell] {
+                    Cell::Letter(c) => pattern.push(Some(c)),
+                    _ => break,
+                }
+                if matcher.count_matches(&pattern) == 0 {
+                    continue;
+                }
+                let cells = line(pos, *step, pattern.len());
+                let intended = placed.iter().any(|placement| *placement == cells || placement.iter().rev().eq(cells.iter()));
+                if !intended {
+                    let word = pattern.iter().map(|c| c.unwrap()).collect();
+                    result.push(HiddenPlacement { word, pos, step: *step });
+                }
+            }
+        }
+    }
+    result
+}
+
+fn line(pos: Pos, step: (isize, isize), length: usize) -> Vec<Pos> {
+    (0..length as isize)
+        .map(|i| Pos((pos.0 as isize + step.0 * i) as usize, (pos.1 as isize + step.1 * i) as usize))
+        .collect()
+}
+
+struct Placer<'a> {
+    crossword: Crossword,
+    options: &'a WordSearchOptions,
+    words: &'a [String],
+    placements: Vec<HiddenPlacement>,
+    nodes: usize,
+    free_cells: Option<usize>,
+}
+
+impl Placer<'_> {
+    fn place(&mut self, index: usize, rng: &mut impl Rng) -> bool {
+        self.nodes += 1;
+        if self.nodes > self.options.max_nodes {
+            return false;
+        }
+        let Some(word) = self.words.get(index) else {
+            let empty = self.crossword.positions().filter(|pos| self.crossword[*pos] == Cell::Empty).count();
+            return self.free_cells.is_none_or(|free| free == empty);
+        };
+        let mut candidates = self.crossword.positions()
+            .flat_map(|pos| self.options.steps.iter().map(move |step| (pos, *step)))
+            .filter(|(pos, step)| self.fits(word, *pos, *step))
+            .collect::<Vec<_>>();
+        candidates.shuffle(rng);
+        for (pos, step) in candidates {
+            let cells = line(pos, step, word.len());
+            let previous = cells.iter().map(|cell| self.crossword[*cell]).collect::<Vec<_>>();
+            for (cell, c) in cells.iter().zip(word.chars()) {
+                self.crossword[*cell] = Cell::Letter(c);
+            }
+            self.placements.push(HiddenPlacement { word: word.clone(), pos, step });
+            if self.place(index + 1, rng) {
+                return true;
+            }
+            self.placements.pop();
+            for (cell, previous) in cells.iter().zip(previous) {
+                self.crossword[*cell] = previous;
+            }
+            if self.nodes > self.options.max_nodes {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn fits(&self, word: &str, pos: Pos, step: (isize, isize)) -> bool {
+        let length = word.len() as isize;
+        let end = (pos.0 as isize + step.0 * (length - 1), pos.1 as isize + step.1 * (length - 1));
+        if end.0 < 0 || end.1 < 0 || end.0 >= self.crossword.width() as isize || end.1 >= self.crossword.height() as isize {
+            return false;
+        }
+        let cells = line(pos, step, word.len());
+        if self.placements.iter().any(|placement| placement.cells() == cells || placement.cells().iter().rev().eq(cells.iter())) {
+            return false;
+        }
+        cells.iter().zip(word.chars()).all(|(cell, c)| match self.crossword[*cell] {
+            Cell::Empty => true,
+            Cell::Letter(existing) => self.options.allow_overlap && existing == c,
+            _ => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+
+    fn spelled(crossword: &Crossword, cells: &[Pos]) -> String {
+        cells.iter().map(|pos| match crossword[*pos] {
+            Cell::Letter(c) => c,
+            _ => '?',
+        }).collect()
+    }
+
+    #[test]
+    fn word_inside_longer_word_is_reported() {
+        let crossword = Crossword::from_str("CATS\nXYZW");
+        let placements = [
+            HiddenPlacement { word: "CATS".to_string(), pos: Pos(0, 0), step: (1, 0) },
+            HiddenPlacement { word: "CAT".to_string(), pos: Pos(0, 1), step: (1, 0) },
+        ];
+        let matcher = Matcher::from_words(["CATS", "CAT"]);
+        let unintended = unintended_occurrences(&crossword, &placements, &matcher, &WordSearchOptions::default());
+        assert!(unintended.contains(&HiddenPlacement { word: "CAT".to_string(), pos: Pos(0, 0), step: (1, 0) }));
+    }
+
+    #[test]
+    fn nested_word_lists_are_rejected() {
+        let mut rng = StdRng::seed_from_u64(1);
+        assert!(generate_word_search(6, 6, &["cats", "cat"], &WordSearchOptions::default(), &mut rng).is_none());
+        assert!(generate_word_search(6, 6, &["stac", "cat"], &WordSearchOptions::default(), &mut rng).is_none());
+    }
+
+    #[test]
+    fn generated_grid_hides_words_and_key_phrase() {
+        let words = ["APPLE", "GRAPE", "LEMON", "MELON", "PEACH"];
+        let options = WordSearchOptions { key_phrase: Some("Sweet fruits!".to_string()), allow_overlap: false, ..Default::default() };
+        let search = generate_word_search(6, 6, &words, &options, &mut StdRng::seed_from_u64(2)).unwrap();
+        for placement in &search.placements {
+            assert_eq!(spelled(&search.crossword, &placement.cells()), placement.word);
+        }
+        assert_eq!(search.placements.len(), words.len());
+        assert_eq!(spelled(&search.crossword, &search.key_cells), "SWEETFRUITS");
+        let matcher = Matcher::from_words(words);
+        assert!(unintended_occurrences(&search.crossword, &search.placements, &matcher, &options).is_empty());
+    }
+}